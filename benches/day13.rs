@@ -0,0 +1,91 @@
+use std::cmp::Ordering;
+use std::time::{Duration, Instant};
+
+use rustaocean2022::days::day13::{decoder_key, PacketValue};
+
+// Small LCG so the generated input is the same on every run
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self, bound: u64) -> u64 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.0 >> 33) % bound
+    }
+}
+
+fn gen_list(rng: &mut Rng, depth: usize) -> PacketValue {
+    let len = rng.next(6);
+    PacketValue::List(
+        (0..len)
+            .map(|_| {
+                if depth < 4 && rng.next(3) == 0 {
+                    gen_list(rng, depth + 1)
+                } else {
+                    // Skipping 2 and 6 keeps packets from tying with the dividers
+                    PacketValue::Value([0, 1, 3, 4, 5, 7, 8, 9, 10][rng.next(9) as usize])
+                }
+            })
+            .collect(),
+    )
+}
+
+// The previous comparison, which wrapped scalars into a fresh Vec on every mixed pair
+#[allow(clippy::useless_vec)]
+fn cloning_cmp(left: &PacketValue, right: &PacketValue) -> Ordering {
+    match (left, right) {
+        (PacketValue::Value(l), PacketValue::Value(r)) => l.cmp(r),
+        (PacketValue::List(l), PacketValue::List(r)) => lists_cmp(l, r),
+        (PacketValue::List(l), r) => lists_cmp(l, &vec![r.clone()]),
+        (l, PacketValue::List(r)) => lists_cmp(&vec![l.clone()], r),
+    }
+}
+
+fn lists_cmp(left: &[PacketValue], right: &[PacketValue]) -> Ordering {
+    left.iter()
+        .zip(right.iter())
+        .map(|(l, r)| cloning_cmp(l, r))
+        .find(|ord| ord.is_ne())
+        .unwrap_or_else(|| left.len().cmp(&right.len()))
+}
+
+fn sorting_decoder_key(packets: &[PacketValue]) -> usize {
+    let dividers = ["[[2]]".parse::<PacketValue>().unwrap(), "[[6]]".parse().unwrap()];
+    let mut all: Vec<&PacketValue> = packets.iter().chain(dividers.iter()).collect();
+    all.sort_by(|a, b| cloning_cmp(a, b));
+    all.iter()
+        .enumerate()
+        .filter(|(_, p)| dividers.iter().any(|d| std::ptr::eq(**p, d)))
+        .map(|(i, _)| i + 1)
+        .product()
+}
+
+fn time<T>(label: &str, runs: u32, mut f: impl FnMut() -> T) -> T {
+    let mut result = f();
+    let mut total = Duration::ZERO;
+    for _ in 0..runs {
+        let now = Instant::now();
+        result = f();
+        total += now.elapsed();
+    }
+    println!("{:<32} {:?}", label, total / runs);
+    result
+}
+
+fn main() {
+    let mut rng = Rng(2022);
+    for size in [1_000, 10_000, 100_000] {
+        let packets: Vec<PacketValue> = (0..size).map(|_| gen_list(&mut rng, 0)).collect();
+        println!("{} packets", size);
+
+        let pairs = |cmp: fn(&PacketValue, &PacketValue) -> Ordering| {
+            packets.chunks(2).filter(|pair| cmp(&pair[0], &pair[1]).is_lt()).count()
+        };
+        let cloned = time("  pairs, cloning cmp", 10, || pairs(cloning_cmp));
+        let borrowed = time("  pairs, borrowing cmp", 10, || pairs(PacketValue::cmp));
+        assert_eq!(cloned, borrowed);
+
+        let sorted = time("  decoder key, sort", 3, || sorting_decoder_key(&packets));
+        let counted = time("  decoder key, count", 10, || decoder_key(&packets));
+        assert_eq!(sorted, counted);
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::str::FromStr;
+
+use crate::points::{Hex, Point, PointN};
+
+pub trait Rule {
+    fn is_alive(&self, was_alive: bool, neighbours: usize) -> bool;
+}
+
+impl<F: Fn(bool, usize) -> bool> Rule for F {
+    fn is_alive(&self, was_alive: bool, neighbours: usize) -> bool {
+        self(was_alive, neighbours)
+    }
+}
+
+// Birth/survival neighbour counts kept as bitmasks, enough for the 80 neighbours of 4D
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct LifeRule {
+    birth: u128,
+    survival: u128,
+}
+
+impl LifeRule {
+    // Counts of 128 and above can never match, so they are left out of the masks
+    pub fn new(birth: &[usize], survival: &[usize]) -> Self {
+        let to_mask = |counts: &[usize]| counts.iter().filter(|c| **c < 128).fold(0u128, |mask, c| mask | (1 << c));
+        Self {
+            birth: to_mask(birth),
+            survival: to_mask(survival),
+        }
+    }
+
+    pub fn conway() -> Self {
+        Self::new(&[3], &[2, 3])
+    }
+
+    fn has(mask: u128, neighbours: usize) -> bool {
+        neighbours < 128 && (mask >> neighbours) & 1 == 1
+    }
+}
+
+impl Rule for LifeRule {
+    fn is_alive(&self, was_alive: bool, neighbours: usize) -> bool {
+        if was_alive {
+            Self::has(self.survival, neighbours)
+        } else {
+            Self::has(self.birth, neighbours)
+        }
+    }
+}
+
+// Parses the usual "B3/S23" notation
+impl FromStr for LifeRule {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (birth, survival) = s.split_once('/').ok_or(())?;
+        let birth = birth.strip_prefix('B').ok_or(())?;
+        let survival = survival.strip_prefix('S').ok_or(())?;
+        let to_counts = |s: &str| {
+            s.chars()
+                .map(|c| c.to_digit(10).map(|d| d as usize).ok_or(()))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(Self::new(&to_counts(birth)?, &to_counts(survival)?))
+    }
+}
+
+// Only active cells are stored, so rules must not give birth with 0 neighbours
+#[derive(Clone, Debug)]
+pub struct Automaton<P, R> {
+    active: HashSet<P>,
+    rule: R,
+    generation: usize,
+}
+
+impl<P, R> Automaton<P, R>
+where
+    P: Point + Copy + Hash + Eq,
+    R: Rule,
+{
+    pub fn new(rule: R) -> Self {
+        Self::from_cells(std::iter::empty(), rule)
+    }
+
+    pub fn from_cells(cells: impl IntoIterator<Item = P>, rule: R) -> Self {
+        Self {
+            active: cells.into_iter().collect(),
+            rule,
+            generation: 0,
+        }
+    }
+
+    pub fn insert(&mut self, cell: P) -> bool {
+        self.active.insert(cell)
+    }
+
+    pub fn remove(&mut self, cell: &P) -> bool {
+        self.active.remove(cell)
+    }
+
+    pub fn is_active(&self, cell: &P) -> bool {
+        self.active.contains(cell)
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = &P> {
+        self.active.iter()
+    }
+
+    pub fn count_active(&self) -> usize {
+        self.active.len()
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    pub fn count_neighbours(&self) -> HashMap<P, u32> {
+        let mut counts = HashMap::with_capacity(self.active.len() * 4);
+        for cell in self.active.iter() {
+            cell.for_each_neighbour(|n| *counts.entry(n).or_insert(0) += 1);
+        }
+        counts
+    }
+
+    pub fn step(&mut self) {
+        let counts = self.count_neighbours();
+
+        let mut next: HashSet<P> = counts
+            .iter()
+            .filter(|(cell, count)| self.rule.is_alive(self.active.contains(cell), **count as usize))
+            .map(|(cell, _)| *cell)
+            .collect();
+
+        // Active cells without any active neighbour never show up in counts
+        if self.rule.is_alive(true, 0) {
+            next.extend(self.active.iter().filter(|cell| !counts.contains_key(cell)));
+        }
+
+        self.active = next;
+        self.generation += 1;
+    }
+
+    pub fn run(&mut self, generations: usize) -> usize {
+        for _ in 0..generations {
+            self.step();
+        }
+        self.count_active()
+    }
+}
+
+impl<const D: usize, R: Rule> Automaton<PointN<D>, R> {
+    // Reads a '#'/'.' picture into the x/y plane, with every other coordinate at 0
+    pub fn from_grid(lines: &[String], rule: R) -> Self {
+        let cells = lines.iter().enumerate().flat_map(|(y, line)| {
+            line.chars()
+                .enumerate()
+                .filter(|(_, c)| *c == '#')
+                .map(move |(x, _)| {
+                    let mut coords = [0; D];
+                    coords[0] = x as i32;
+                    coords[1] = y as i32;
+                    PointN::new(coords)
+                })
+        });
+        Self::from_cells(cells, rule)
+    }
+
+    pub fn bounds(&self) -> Option<(PointN<D>, PointN<D>)> {
+        let first = *self.active.iter().next()?;
+        Some(
+            self.active
+                .iter()
+                .fold((first, first), |(lo, hi), p| (lo.component_min(p), hi.component_max(p))),
+        )
+    }
+
+    // Draws the x/y plane at the given values of the remaining D - 2 coordinates
+    pub fn render_slice(&self, rest: &[i32]) -> String {
+        assert_eq!(rest.len() + 2, D, "Slice needs {} fixed coordinates", D - 2);
+        let (lo, hi) = match self.bounds() {
+            Some(bounds) => bounds,
+            None => return String::new(),
+        };
+
+        let mut coords = [0; D];
+        coords[2..].copy_from_slice(rest);
+        (lo[1]..=hi[1])
+            .map(|y| {
+                (lo[0]..=hi[0])
+                    .map(|x| {
+                        coords[0] = x;
+                        coords[1] = y;
+                        if self.active.contains(&PointN::new(coords)) {
+                            '#'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl<R: Rule> Automaton<Hex, R> {
+    pub fn bounds(&self) -> Option<(Hex, Hex)> {
+        let first = self.active.iter().next()?.to_point();
+        let (lo, hi) = self.active.iter().fold((first, first), |(lo, hi), hex| {
+            let p = hex.to_point();
+            (lo.component_min(&p), hi.component_max(&p))
+        });
+        Some((Hex::new(lo.x(), lo.y()), Hex::new(hi.x(), hi.y())))
+    }
+
+    // Each row is shifted by half a cell per r so the picture keeps its hex layout
+    pub fn render(&self) -> String {
+        let (lo, hi) = match self.bounds() {
+            Some(bounds) => bounds,
+            None => return String::new(),
+        };
+
+        (lo.r()..=hi.r())
+            .map(|r| {
+                let indent = " ".repeat((r - lo.r()) as usize);
+                let cells = (lo.q()..=hi.q())
+                    .map(|q| if self.active.contains(&Hex::new(q, r)) { "#" } else { "." })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                indent + &cells
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
@@ -1,33 +1,14 @@
 use std::str::FromStr;
 
 use crate::common::Solution;
-
-#[derive(Debug)]
-struct Assignment {
-    from: i32,
-    to: i32
-}
-
-impl Assignment {
-    fn contains(&self, other: &Assignment) -> bool {
-        self.from <= other.from && self.to >= other.to
-    }
-
-    fn overlaps_start_of(&self, other: &Assignment) -> bool {
-        self.from <= other.from && self.to >= other.from
-    }
-}
-
-impl FromStr for Assignment {
-    type Err = ();
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if let Some((from, to)) = s.split_once('-') {
-            let (from, to) = (from.parse().unwrap(), to.parse().unwrap());
-            Ok(Assignment { from, to })
-        } else {
-            Err(())
-        }
+use crate::interval::Interval;
+
+fn parse_assignment(s: &str) -> Result<Interval, ()> {
+    if let Some((from, to)) = s.split_once('-') {
+        let (from, to) = (from.parse().unwrap(), to.parse().unwrap());
+        Ok(Interval::inclusive(from, to))
+    } else {
+        Err(())
     }
 }
 
@@ -36,7 +17,7 @@ impl FromStr for Pair {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if let Some((first, second)) = s.split_once(',') {
-            let (first, second) = (first.parse().unwrap(), second.parse().unwrap());
+            let (first, second) = (parse_assignment(first)?, parse_assignment(second)?);
             Ok(Pair(first, second))
         } else {
             Err(())
@@ -45,15 +26,15 @@ impl FromStr for Pair {
 }
 
 #[derive(Debug)]
-struct Pair (Assignment, Assignment);
+struct Pair (Interval, Interval);
 
 impl Pair {
     fn is_contained(&self) -> bool {
-        self.0.contains(&self.1) || self.1.contains(&self.0)
+        self.0.contains_interval(&self.1) || self.1.contains_interval(&self.0)
     }
 
     fn is_overlapped(&self) -> bool {
-        self.0.overlaps_start_of(&self.1) || self.1.overlaps_start_of(&self.0)
+        self.0.overlaps(&self.1)
     }
 }
 
@@ -1,116 +1,454 @@
-use std::str::FromStr;
+use std::{cmp::Ordering, fmt, str::FromStr};
 
 use itertools::Itertools;
 
 use crate::common::Solution;
 
-#[derive(Debug, Clone, Copy)]
-struct Move {
-    num: u8,
-    from: usize,
-    to: usize
+pub type Crate = String;
+
+// Stacks are numbered from 1, as in the puzzle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    pub num: usize,
+    pub from: usize,
+    pub to: usize,
+}
+
+impl Move {
+    // Moving the same crates back puts them exactly where they were, with either crane
+    pub fn reversed(&self) -> Move {
+        Move { num: self.num, from: self.to, to: self.from }
+    }
 }
 
 impl FromStr for Move {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if let Some(stripped) = s.strip_prefix("move ") {
-            let (num, rest) = stripped.split_once(" from ").unwrap();
-            let (from, to) = rest.split_once(" to ").unwrap();
-
-            Ok(Move {
-                num: num.parse().unwrap(),
-                from: from.parse().unwrap(),
-                to: to.parse().unwrap()
-            })
-        } else {
-            Err(())
+        let stripped = s.strip_prefix("move ").ok_or(())?;
+        let (num, rest) = stripped.split_once(" from ").ok_or(())?;
+        let (from, to) = rest.split_once(" to ").ok_or(())?;
+
+        Ok(Move {
+            num: num.parse().map_err(|_| ())?,
+            from: from.parse().map_err(|_| ())?,
+            to: to.parse().map_err(|_| ())?,
+        })
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "move {} from {} to {}", self.num, self.from, self.to)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoveErrorKind {
+    NoSuchStack(usize),
+    NotEnoughCrates { stack: usize, has: usize },
+}
+
+// Step is the 0-based position of the move in the list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveError {
+    pub step: usize,
+    pub mv: Move,
+    pub kind: MoveErrorKind,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "step {} ({}): ", self.step, self.mv)?;
+        match self.kind {
+            MoveErrorKind::NoSuchStack(stack) => write!(f, "there is no stack {}", stack),
+            MoveErrorKind::NotEnoughCrates { stack, has } => {
+                write!(f, "stack {} only holds {} crates", stack, has)
+            }
         }
     }
 }
 
-#[derive(Debug, Clone)]
-struct StackData {
-    stacks: Vec<Vec<char>>,
-    moves: Vec<Move>
+impl std::error::Error for MoveError {}
+
+pub trait Crane {
+    // Takes `num` crates off the top of `stack`, in the order they land on the target
+    fn lift(&self, stack: &mut Vec<Crate>, num: usize) -> Vec<Crate>;
 }
 
-impl StackData {
-    fn perform_moves(&mut self) {
-        self.moves.iter()
-        .flat_map(|m| std::iter::repeat((m.from, m.to)).take(m.num.into()))
-        .for_each(|(from, to)| {
-            if let Some(elem)  = self.stacks.get_mut(from - 1).unwrap().pop() {
-                self.stacks.get_mut(to - 1).unwrap().push(elem);
+// Moves crates one at a time, so a batch lands upside down
+pub struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn lift(&self, stack: &mut Vec<Crate>, num: usize) -> Vec<Crate> {
+        let mut lifted = stack.split_off(stack.len() - num);
+        lifted.reverse();
+        lifted
+    }
+}
+
+// Moves the whole batch at once, keeping its order
+pub struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn lift(&self, stack: &mut Vec<Crate>, num: usize) -> Vec<Crate> {
+        stack.split_off(stack.len() - num)
+    }
+}
+
+pub struct Simulator<'a, C: Crane> {
+    crane: C,
+    stacks: Vec<Vec<Crate>>,
+    moves: &'a [Move],
+    applied: usize,
+}
+
+impl<'a, C: Crane> Simulator<'a, C> {
+    pub fn new(stacks: Vec<Vec<Crate>>, moves: &'a [Move], crane: C) -> Self {
+        Simulator { crane, stacks, moves, applied: 0 }
+    }
+
+    pub fn stacks(&self) -> &[Vec<Crate>] {
+        &self.stacks
+    }
+
+    // Number of moves performed so far
+    pub fn applied(&self) -> usize {
+        self.applied
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.applied == self.moves.len()
+    }
+
+    fn validate(&self, mv: &Move) -> Result<(), MoveErrorKind> {
+        for stack in [mv.from, mv.to] {
+            if stack == 0 || stack > self.stacks.len() {
+                return Err(MoveErrorKind::NoSuchStack(stack));
             }
-        });
+        }
+        let has = self.stacks[mv.from - 1].len();
+        if has < mv.num {
+            return Err(MoveErrorKind::NotEnoughCrates { stack: mv.from, has });
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, mv: &Move) {
+        let mut lifted = self.crane.lift(&mut self.stacks[mv.from - 1], mv.num);
+        self.stacks[mv.to - 1].append(&mut lifted);
+    }
+
+    // Performs the next move, or returns None once all are done.
+    // An invalid move leaves the stacks untouched.
+    pub fn step(&mut self) -> Result<Option<Move>, MoveError> {
+        let Some(mv) = self.moves.get(self.applied).copied() else {
+            return Ok(None);
+        };
+        self.validate(&mv).map_err(|kind| MoveError { step: self.applied, mv, kind })?;
+        self.apply(&mv);
+        self.applied += 1;
+        Ok(Some(mv))
     }
 
-    fn perform_moves_batched(&mut self) {
-        self.moves.iter()
-        .for_each(|Move{num, from, to}| {
-            let from  = self.stacks.get_mut(from - 1).unwrap();
-            let mut from_elems = from.split_off(from.len() - *num as usize);
-            self.stacks.get_mut(to - 1).unwrap().append(&mut from_elems);
-        });
+    // Takes back the last performed move
+    pub fn undo(&mut self) -> Option<Move> {
+        self.applied = self.applied.checked_sub(1)?;
+        let mv = self.moves[self.applied];
+        self.apply(&mv.reversed());
+        Some(mv)
     }
 
-    fn get_top_values(&self) -> String {
-        self.stacks.iter()
-        .filter_map(|stack| stack.last())
-        .collect()
+    // Steps or undoes until exactly `applied` moves are performed
+    pub fn seek(&mut self, applied: usize) -> Result<(), MoveError> {
+        while self.applied > applied {
+            self.undo();
+        }
+        while self.applied < applied.min(self.moves.len()) {
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    pub fn run(&mut self) -> Result<(), MoveError> {
+        self.seek(self.moves.len())
+    }
+
+    pub fn get_top_values(&self) -> String {
+        self.stacks.iter().filter_map(|stack| stack.last().map(|c| c.as_str())).collect()
     }
 }
 
-fn part1(input: &InputType) -> String {
-    let mut input_cloned = input.clone();
-    input_cloned.perform_moves();
-    input_cloned.get_top_values().to_string()
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DrawingErrorKind {
+    MissingLabels,
+    BadLabel { expected: usize, found: String },
+    UnclosedCrate,
+    UnexpectedText(char),
+    // A crate with nothing under it
+    Floating { stack: usize },
+    // Two crates in the same row closest to one label
+    Overlapping { stack: usize },
 }
 
-fn part2(input: &mut InputType) -> String {
-    input.perform_moves_batched();
-    input.get_top_values().to_string()
+// Line and column are 1-based within the drawing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DrawingError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: DrawingErrorKind,
 }
 
-fn parse_stacks(input: &Vec<Vec<char>>) -> Vec<Vec<char>> {
-    let num_row = input.last().unwrap();
-    num_row.iter()
-    .enumerate()
-    .filter(|(_, r)| r.is_numeric())
-    .map(|(i, _)|  input.iter()
+impl fmt::Display for DrawingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            DrawingErrorKind::MissingLabels => write!(f, "no row of stack labels"),
+            DrawingErrorKind::BadLabel { expected, found } => {
+                write!(f, "expected stack label {}, found '{}'", expected, found)
+            }
+            DrawingErrorKind::UnclosedCrate => write!(f, "crate is missing its closing ']'"),
+            DrawingErrorKind::UnexpectedText(c) => write!(f, "unexpected '{}'", c),
+            DrawingErrorKind::Floating { stack } => write!(f, "crate on stack {} has nothing under it", stack),
+            DrawingErrorKind::Overlapping { stack } => write!(f, "second crate for stack {} in one row", stack),
+        }
+    }
+}
+
+impl std::error::Error for DrawingError {}
+
+// Runs of non-space characters in a line, with the column they start at
+fn tokens(line: &str) -> Vec<(usize, Vec<char>)> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut result = Vec::new();
+    let mut col = 0;
+    while col < chars.len() {
+        if chars[col] == ' ' {
+            col += 1;
+            continue;
+        }
+        let start = col;
+        while col < chars.len() && chars[col] != ' ' {
+            col += 1;
+        }
+        result.push((start, chars[start..col].to_vec()));
+    }
+    result
+}
+
+// The last line holds the stack labels 1, 2, ... and every `[crate]` above
+// belongs to the label nearest to its middle, so lines may be ragged, labels
+// may be several digits wide and crates may have longer names.
+pub fn parse_drawing(lines: &[String]) -> Result<Vec<Vec<Crate>>, DrawingError> {
+    let (label_line, crate_lines) = lines.split_last().ok_or(DrawingError {
+        line: 1,
+        column: 1,
+        kind: DrawingErrorKind::MissingLabels,
+    })?;
+
+    let labels = tokens(label_line);
+    let mut centers = Vec::new();
+    for (idx, (col, label)) in labels.iter().enumerate() {
+        let found: String = label.iter().collect();
+        if found.parse() != Ok(idx + 1) {
+            let kind = DrawingErrorKind::BadLabel { expected: idx + 1, found };
+            return Err(DrawingError { line: lines.len(), column: col + 1, kind });
+        }
+        centers.push(2 * col + label.len() - 1);
+    }
+    if centers.is_empty() {
+        return Err(DrawingError { line: lines.len(), column: 1, kind: DrawingErrorKind::MissingLabels });
+    }
+
+    // Bottom up, so every crate can check there is one below it
+    let mut stacks: Vec<Vec<Crate>> = vec![Vec::new(); centers.len()];
+    for (row, line) in crate_lines.iter().enumerate().rev() {
+        let err = |col: usize, kind| DrawingError { line: row + 1, column: col + 1, kind };
+        let chars: Vec<char> = line.chars().collect();
+        let level = crate_lines.len() - 1 - row;
+        let mut col = 0;
+        while col < chars.len() {
+            match chars[col] {
+                ' ' => col += 1,
+                '[' => {
+                    let len = chars[col..].iter().position(|c| *c == ']').ok_or(err(col, DrawingErrorKind::UnclosedCrate))?;
+                    let center = 2 * col + len;
+                    let stack = (0..centers.len()).min_by_key(|i| centers[*i].abs_diff(center)).unwrap();
+                    match stacks[stack].len().cmp(&level) {
+                        Ordering::Less => return Err(err(col, DrawingErrorKind::Floating { stack: stack + 1 })),
+                        Ordering::Greater => return Err(err(col, DrawingErrorKind::Overlapping { stack: stack + 1 })),
+                        Ordering::Equal => stacks[stack].push(chars[col + 1..col + len].iter().collect()),
+                    }
+                    col += len + 1;
+                }
+                c => return Err(err(col, DrawingErrorKind::UnexpectedText(c))),
+            }
+        }
+    }
+    Ok(stacks)
+}
+
+// The same layout the puzzle uses: columns wide enough for the longest crate
+// or label, one space apart, crates stacked up from the label row
+pub fn render_drawing(stacks: &[Vec<Crate>]) -> String {
+    let widths: Vec<usize> = stacks
+        .iter()
+        .enumerate()
+        .map(|(i, stack)| {
+            let crate_width = stack.iter().map(|c| c.chars().count() + 2).max().unwrap_or(3);
+            crate_width.max((i + 1).to_string().len())
+        })
+        .collect();
+    let height = stacks.iter().map(|stack| stack.len()).max().unwrap_or(0);
+
+    let row = |cell: &dyn Fn(usize) -> String| {
+        widths.iter().enumerate().map(|(i, w)| format!("{:^w$}", cell(i), w = *w)).join(" ")
+    };
+    let mut lines: Vec<String> = (0..height)
         .rev()
-        .skip(1)
-        .map(move |row| row.get(i).unwrap())
-        .take_while(|val| val.is_ascii_alphabetic())
-        .copied()
-        .collect_vec()
-    )
-    .collect_vec()
+        .map(|level| row(&|i| stacks[i].get(level).map_or(String::new(), |c| format!("[{}]", c))))
+        .collect();
+    lines.push(row(&|i| (i + 1).to_string()));
+    lines.join("\n")
+}
+
+#[derive(Debug, Clone)]
+pub struct StackData {
+    pub stacks: Vec<Vec<Crate>>,
+    pub moves: Vec<Move>,
+}
+
+impl StackData {
+    pub fn simulate<C: Crane>(&self, crane: C) -> Simulator<'_, C> {
+        Simulator::new(self.stacks.clone(), &self.moves, crane)
+    }
+
+    pub fn render(&self) -> String {
+        render_drawing(&self.stacks)
+    }
+}
+
+fn run_crane(input: &InputType, crane: impl Crane) -> String {
+    let mut sim = input.simulate(crane);
+    sim.run().unwrap();
+    sim.get_top_values()
+}
+
+fn part1(input: &InputType) -> String {
+    run_crane(input, CrateMover9000)
+}
+
+fn part2(input: &InputType) -> String {
+    run_crane(input, CrateMover9001)
 }
 
 type InputType = StackData;
 fn parse_input(raw_input: &[String]) -> InputType {
-    let stack_info = raw_input.iter()
-    .take_while(|line| !line.is_empty())
-    .map(|line| line.chars().into_iter().collect_vec())
-    .collect_vec();
-
-    let stacks = parse_stacks(&stack_info);
+    let drawing = raw_input.iter().take_while(|line| !line.is_empty()).cloned().collect_vec();
+    let stacks = parse_drawing(&drawing).unwrap();
 
-    let moves = raw_input.iter()
-    .filter_map(|line| line.parse().ok())
-    .collect_vec();
+    let moves = raw_input
+        .iter()
+        .skip(drawing.len())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.parse().unwrap())
+        .collect_vec();
     StackData { stacks, moves }
 }
 
 pub fn solve(raw_input: &[String]) -> Solution {
-    let mut input = parse_input(raw_input);
+    let input = parse_input(raw_input);
 
     use std::time::Instant;
     let now = Instant::now();
-    let solution = (part1(&input), part2(&mut input));
+    let solution = (part1(&input), part2(&input));
     let elapsed = now.elapsed();
     (solution, elapsed)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_stacks() -> Vec<Vec<Crate>> {
+        [vec!["Z", "N"], vec!["M", "C", "D"], vec!["P"]]
+            .iter()
+            .map(|stack| stack.iter().map(|c| c.to_string()).collect())
+            .collect()
+    }
+
+    fn moves(lines: &[&str]) -> Vec<Move> {
+        lines.iter().map(|line| line.parse().unwrap()).collect()
+    }
+
+    const EXAMPLE_MOVES: [&str; 4] =
+        ["move 1 from 2 to 1", "move 3 from 1 to 3", "move 2 from 2 to 1", "move 1 from 1 to 2"];
+
+    fn check_invalid_move<C: Crane>(crane: impl Fn() -> C) {
+        let moves = moves(&["move 1 from 2 to 1", "move 1 from 4 to 1", "move 1 from 1 to 0", "move 5 from 3 to 1"]);
+        for (bad, kind) in [
+            (1, MoveErrorKind::NoSuchStack(4)),
+            (2, MoveErrorKind::NoSuchStack(0)),
+            (3, MoveErrorKind::NotEnoughCrates { stack: 3, has: 1 }),
+        ] {
+            let list = [moves[0], moves[bad]];
+            let mut sim = Simulator::new(example_stacks(), &list, crane());
+            sim.step().unwrap();
+            let before = sim.stacks().to_vec();
+            assert_eq!(sim.step(), Err(MoveError { step: 1, mv: moves[bad], kind }));
+            assert_eq!(sim.stacks(), before.as_slice());
+            assert_eq!(sim.applied(), 1);
+        }
+    }
+
+    fn check_seek_restores<C: Crane>(crane: C, tops: &str) {
+        let moves = moves(&EXAMPLE_MOVES);
+        let mut sim = Simulator::new(example_stacks(), &moves, crane);
+        for n in 0..=moves.len() {
+            sim.seek(n).unwrap();
+            assert_eq!(sim.applied(), n);
+            sim.seek(0).unwrap();
+            assert_eq!(sim.stacks(), example_stacks().as_slice());
+        }
+        sim.run().unwrap();
+        assert_eq!(sim.get_top_values(), tops);
+    }
+
+    #[test]
+    fn invalid_moves_leave_stacks_untouched() {
+        check_invalid_move(|| CrateMover9000);
+        check_invalid_move(|| CrateMover9001);
+    }
+
+    #[test]
+    fn seeking_back_restores_the_start() {
+        check_seek_restores(CrateMover9000, "CMZ");
+        check_seek_restores(CrateMover9001, "MCD");
+    }
+
+    fn lines(drawing: &str) -> Vec<String> {
+        drawing.lines().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_trimmed_drawing() {
+        let drawing = ["    [D]", "[N] [C]", "[Z] [M] [P]", " 1   2   3"].map(String::from);
+        assert_eq!(parse_drawing(&drawing), Ok(example_stacks()));
+        let trimmed: Vec<String> = render_drawing(&example_stacks()).lines().map(|l| l.trim_end().to_string()).collect();
+        assert_eq!(trimmed, drawing);
+    }
+
+    #[test]
+    fn wide_drawing_round_trips() {
+        let stacks: Vec<Vec<Crate>> = (0..12)
+            .map(|i| (0..(i * 5 % 7)).map(|level| format!("C{}{}", i, "x".repeat((i + level) % 6))).collect())
+            .collect();
+        let drawing = render_drawing(&stacks);
+        let labels = drawing.lines().last().unwrap().split_whitespace().map(String::from);
+        assert!(labels.eq((1..=12).map(|i| i.to_string())));
+        assert_eq!(parse_drawing(&lines(&drawing)), Ok(stacks.clone()));
+
+        let ragged: Vec<String> = drawing.lines().map(|l| l.trim_end().to_string()).collect();
+        assert_eq!(parse_drawing(&ragged), Ok(stacks));
+    }
+}
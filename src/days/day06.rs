@@ -1,82 +1,197 @@
-use std::{collections::HashMap};
-
-use itertools::Itertools;
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufReader, Read};
 
 use crate::common::Solution;
 
-struct LetterCounter {
-    counter: HashMap<char, u8>,
-    adds_performed: usize
+// How often each symbol occurs in the window. Bytes fit a fixed table, chars
+// go into a map that only ever holds the symbols currently in the window.
+enum SymbolCounts {
+    Table(Box<[u32; 256]>),
+    Map(HashMap<u32, u32>),
 }
 
-impl LetterCounter {
-    fn new() -> Self {
-        Self { counter: HashMap::new(), adds_performed: 0 }
+impl SymbolCounts {
+    fn new(unit: Unit) -> Self {
+        match unit {
+            Unit::Bytes => SymbolCounts::Table(Box::new([0; 256])),
+            Unit::Chars => SymbolCounts::Map(HashMap::new()),
+        }
     }
 
-    fn add(&mut self, letter: &char) {
-        self.adds_performed += 1;
-        *self.counter.entry(*letter).or_insert(0) += 1;
+    // Count after adding the symbol
+    fn increment(&mut self, symbol: u32) -> u32 {
+        let count = match self {
+            SymbolCounts::Table(table) => &mut table[symbol as usize],
+            SymbolCounts::Map(map) => map.entry(symbol).or_insert(0),
+        };
+        *count += 1;
+        *count
     }
 
-    fn remove(&mut self, letter: &char) {
-        let mut remove_entry = false;
-        if let Some(entry) = self.counter.get_mut(letter) {
-            remove_entry = *entry == 1;
-            *entry -= 1;
+    // Count after removing the symbol
+    fn decrement(&mut self, symbol: u32) -> u32 {
+        match self {
+            SymbolCounts::Table(table) => {
+                table[symbol as usize] -= 1;
+                table[symbol as usize]
+            }
+            SymbolCounts::Map(map) => {
+                let count = map.get_mut(&symbol).unwrap();
+                *count -= 1;
+                let left = *count;
+                if left == 0 {
+                    map.remove(&symbol);
+                }
+                left
+            }
         }
+    }
 
-        if remove_entry {
-            self.counter.remove(letter);
+    fn clear(&mut self, window: &VecDeque<u32>) {
+        match self {
+            SymbolCounts::Table(table) => window.iter().for_each(|s| table[*s as usize] = 0),
+            SymbolCounts::Map(map) => map.clear(),
         }
     }
+}
 
-    fn is_valid(&self) -> bool {
-        self.counter.values().all(|val| val <= &1)
-    }
+// Sliding window that knows in O(1) whether its symbols are all different.
+// With Unit::Bytes every symbol has to be below 256.
+pub struct MarkerFinder {
+    size: usize,
+    window: VecDeque<u32>,
+    counts: SymbolCounts,
+    duplicates: usize,
+    position: usize,
 }
 
-fn find_unique_quartet(input: &String) -> Option<usize> {
-    if let Some(window) = input.chars()
-    .enumerate()
-    .tuple_windows()
-    .find(|((_, a),(_, b),(_, c),(_, d))| a != b && a != c && a != d && b != c && b != d && c != d) {
-        let last_idx = window.3.0;
-        Some(last_idx + 1)
-    } else {
-        None
+impl MarkerFinder {
+    pub fn new(size: usize, unit: Unit) -> Self {
+        MarkerFinder {
+            size,
+            window: VecDeque::with_capacity(size + 1),
+            counts: SymbolCounts::new(unit),
+            duplicates: 0,
+            position: 0,
+        }
+    }
+
+    // Symbols fed so far, which is also the marker position after a successful push
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    // Feeds one symbol, true when the last `size` symbols are all different
+    pub fn push(&mut self, symbol: u32) -> bool {
+        self.position += 1;
+        if self.counts.increment(symbol) == 2 {
+            self.duplicates += 1;
+        }
+        self.window.push_back(symbol);
+
+        if self.window.len() > self.size {
+            let old = self.window.pop_front().unwrap();
+            if self.counts.decrement(old) == 1 {
+                self.duplicates -= 1;
+            }
+        }
+        self.window.len() == self.size && self.duplicates == 0
+    }
+
+    // Starts a new datastream
+    pub fn reset(&mut self) {
+        self.counts.clear(&self.window);
+        self.window.clear();
+        self.duplicates = 0;
+        self.position = 0;
     }
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Unit {
+    Bytes,
+    Chars,
+}
+
+fn utf8_len(lead: u8) -> usize {
+    match lead {
+        0x00..=0x7F => 1,
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        _ => 4,
+    }
+}
 
-fn find_unique_n(input: &String, n: usize) -> Option<usize> {
-    let mut counter = LetterCounter::new();
-    input.chars().take(n).for_each(|c| counter.add(&c));
+// Every marker position in every line of the stream, each line being its own
+// datastream. Positions count bytes or Unicode scalars depending on `unit`.
+pub fn find_markers(reader: impl Read, size: usize, unit: Unit) -> io::Result<Vec<Vec<usize>>> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "stream is not valid UTF-8");
+    let mut bytes = BufReader::new(reader).bytes();
+    let mut finder = MarkerFinder::new(size, unit);
+    let mut lines = vec![Vec::new()];
+    while let Some(byte) = bytes.next() {
+        let byte = byte?;
+        let symbol = match (byte, unit) {
+            (b'\n', _) => {
+                finder.reset();
+                lines.push(Vec::new());
+                continue;
+            }
+            (b'\r', _) => continue,
+            (_, Unit::Bytes) => byte as u32,
+            (_, Unit::Chars) => {
+                let mut buf = [byte, 0, 0, 0];
+                let len = utf8_len(byte);
+                for slot in buf.iter_mut().take(len).skip(1) {
+                    *slot = bytes.next().ok_or_else(invalid)??;
+                }
+                let c = std::str::from_utf8(&buf[..len]).map_err(|_| invalid())?.chars().next().unwrap();
+                c as u32
+            }
+        };
+
+        if finder.push(symbol) {
+            lines.last_mut().unwrap().push(finder.position());
+        }
+    }
 
-    if counter.is_valid() {
-        return Some(n);
+    // Nothing was fed after a trailing newline
+    if lines.len() > 1 && finder.position() == 0 {
+        lines.pop();
     }
+    Ok(lines)
+}
 
-    input.chars()
-    .zip(input.chars().skip(n))
-    .find(|(prev, next)|  {
-        counter.remove(prev);
-        counter.add(next);
-        counter.is_valid()
-    }).and(Some(counter.adds_performed))
+// First marker of every line, None for lines without one
+pub fn first_markers(input: &str, n: usize) -> Vec<Option<usize>> {
+    find_markers(input.as_bytes(), n, Unit::Chars)
+        .unwrap()
+        .iter()
+        .map(|markers| markers.first().copied())
+        .collect()
+}
+
+// One answer per datastream, any line without a marker is an error
+fn find_first_markers(input: &InputType, n: usize) -> Result<String, String> {
+    let markers = first_markers(input, n);
+    let missing: Vec<String> = (1..=markers.len()).filter(|line| markers[line - 1].is_none()).map(|line| line.to_string()).collect();
+    if !missing.is_empty() {
+        return Err(format!("no marker of size {} in line {}", n, missing.join(", ")));
+    }
+    Ok(markers.into_iter().flatten().map(|m| m.to_string()).collect::<Vec<_>>().join(","))
 }
 
 fn part1(input: &InputType) -> String {
-    find_unique_n(input, 4).unwrap().to_string()
+    find_first_markers(input, 4).unwrap_or_else(|e| panic!("{}", e))
 }
 
 fn part2(input: &InputType) -> String {
-    find_unique_n(input, 14).unwrap().to_string()
+    find_first_markers(input, 14).unwrap_or_else(|e| panic!("{}", e))
 }
 
 type InputType = String;
 fn parse_input(raw_input: &[String]) -> InputType {
-    raw_input.first().unwrap().to_owned()
+    raw_input.iter().filter(|line| !line.is_empty()).cloned().collect::<Vec<_>>().join("\n")
 }
 
 pub fn solve(raw_input: &[String]) -> Solution {
@@ -88,3 +203,25 @@ pub fn solve(raw_input: &[String]) -> Solution {
     let elapsed = now.elapsed();
     (solution, elapsed)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_markers_in_bytes_and_chars() {
+        let stream = "mjqjpqmgbljsphdztnvjfqwrcgsmlb\nzółćźż";
+        assert_eq!(find_markers(stream.as_bytes(), 4, Unit::Bytes).unwrap()[0][0], 7);
+        assert_eq!(find_markers(stream.as_bytes(), 4, Unit::Chars).unwrap()[1], vec![4, 5, 6]);
+        assert_eq!(find_markers(stream.as_bytes(), 6, Unit::Chars).unwrap()[1], vec![6]);
+        assert_eq!(find_markers(stream.as_bytes(), 4, Unit::Bytes).unwrap()[1], vec![4, 5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn reports_lines_without_a_marker() {
+        let input = "bvwbjplbgvbhsrlpgdmjqwftvncz\naabbaabb\nnppdvjthqldpwncqszvftbrmjlhg\nabc".to_string();
+        assert_eq!(first_markers(&input, 4), vec![Some(5), None, Some(6), None]);
+        assert_eq!(find_first_markers(&input, 4), Err("no marker of size 4 in line 2, 4".to_string()));
+        assert_eq!(find_first_markers(&"bvwbjplbgvbhsrlpgdmjqwftvncz\nnppdvjthqldpwncqszvftbrmjlhg".to_string(), 4), Ok("5,6".to_string()));
+    }
+}
@@ -1,163 +1,540 @@
-use std::{fs::File, collections::HashMap};
-
-use itertools::Itertools;
+use std::{collections::HashMap, fmt, ops::RangeBounds};
 
 use crate::common::Solution;
 
+pub type NodeId = usize;
+pub const ROOT: NodeId = 0;
 
-//type NodeLink = Option<Box<NodeData>>;
-struct NodeData {
-    parent: Option<String>,
-    children: Vec<String>,
-    node_type: NodeType,
-    size: usize
+// Directories hold the total size of everything below them once the tree is built
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Node {
+    pub name: String,
+    pub parent: Option<NodeId>,
+    pub children: Vec<NodeId>,
+    pub is_dir: bool,
+    pub size: usize,
 }
 
-impl NodeData {
-    fn new(parent: Option<String>, node_type: NodeType, size: usize) -> Self {
-        NodeData { parent, children: Vec::new(), node_type, size }
-    }
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum FsErrorKind {
+    UnknownCommand(String),
+    MalformedEntry(String),
+    SizeConflict { path: String, old: usize, new: usize },
+    KindConflict { path: String },
+}
+
+// Line is 1-based within the transcript
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FsError {
+    pub line: usize,
+    pub kind: FsErrorKind,
+}
 
-    fn new_dir(parent: Option<String>) -> Self {
-        Self::new(parent, NodeType::Dir, 0)
+impl fmt::Display for FsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            FsErrorKind::UnknownCommand(cmd) => write!(f, "unknown command '{}'", cmd),
+            FsErrorKind::MalformedEntry(entry) => write!(f, "malformed ls entry '{}'", entry),
+            FsErrorKind::SizeConflict { path, old, new } => {
+                write!(f, "{} listed with size {} after {}", path, new, old)
+            }
+            FsErrorKind::KindConflict { path } => write!(f, "{} is listed both as a file and a directory", path),
+        }
     }
+}
+
+impl std::error::Error for FsError {}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Disk {
+    pub capacity: usize,
+    pub required: usize,
+}
+
+impl Disk {
+    pub const PUZZLE: Disk = Disk { capacity: 70_000_000, required: 30_000_000 };
 
-    fn new_file(parent: Option<String>, size: usize) -> Self {
-        Self::new(parent, NodeType::File, size)
+    // Bytes that still have to go, zero when there already is enough room
+    pub fn space_to_free(&self, fs: &Filesystem) -> usize {
+        (fs.used_space() + self.required).saturating_sub(self.capacity)
     }
 }
 
-#[derive(PartialEq, Eq)]
-enum NodeType {
-    Dir,
-    File
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct DeletionPlan {
+    pub dirs: Vec<NodeId>,
+    pub freed: usize,
 }
 
-struct Filesystem {
-    filemap: HashMap<String, NodeData>,
+// Set of byte counts min..=max, one bit each
+#[derive(Clone)]
+struct SumSet {
+    min: usize,
+    max: usize,
+    bits: Vec<u64>,
 }
 
-impl Filesystem {
-    fn new() -> Self {
-        Self { filemap: HashMap::new() }
+impl SumSet {
+    fn empty((min, max): (usize, usize)) -> Self {
+        let words = if min > max { 0 } else { (max - min) / 64 + 1 };
+        SumSet { min, max, bits: vec![0; words] }
+    }
+
+    fn zero((min, max): (usize, usize)) -> Self {
+        let mut set = Self::empty((min, max));
+        if min == 0 && !set.bits.is_empty() {
+            set.bits[0] = 1;
+        }
+        set
     }
 
-    fn build(&mut self, input: &Vec<String>) {
-        let mut current_node = "".to_owned();
-        self.filemap.insert(current_node.to_string(), NodeData::new_dir(None));
+    fn contains(&self, val: usize) -> bool {
+        (self.min..=self.max).contains(&val) && self.bits[(val - self.min) / 64] >> ((val - self.min) % 64) & 1 == 1
+    }
 
-        for line in input {
-            // Handle going out
-            if line.eq("$ cd ..") {
-                if let Some(entry) = self.filemap.get(&current_node) {
-                    if let Some(parent_name) = &entry.parent {
-                        current_node = parent_name.to_string();
-                    }
-                }
-                continue;
+    // self |= other + shift, dropping whatever falls outside of self
+    fn add_shifted(&mut self, other: &SumSet, shift: usize) {
+        // Bit j of other lands on bit j + delta of self, so word i of self is
+        // made of words i + skip and i + skip + 1 of other
+        let delta = (other.min + shift) as isize - self.min as isize;
+        let (skip, offset) = ((-delta).div_euclid(64), (-delta).rem_euclid(64) as u32);
+        let word = |w: isize| if (0..other.bits.len() as isize).contains(&w) { other.bits[w as usize] } else { 0 };
+        let first = (-skip - 1).max(0) as usize;
+        let last = (other.bits.len() as isize - skip).clamp(0, self.bits.len() as isize) as usize;
+        for i in first..last {
+            let src = i as isize + skip;
+            let high = if offset > 0 { word(src + 1) << (64 - offset) } else { 0 };
+            self.bits[i] |= word(src) >> offset | high;
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        let min = self.min;
+        self.bits
+            .iter()
+            .enumerate()
+            .flat_map(move |(i, word)| (0..64).filter(move |b| word >> b & 1 == 1).map(move |b| min + i * 64 + b))
+            .take_while(|v| *v <= self.max)
+    }
+}
+
+// Deleting directories none of which contains another is a path through the
+// directories in preorder: at each one either step to the next, or delete it
+// and jump past its subtree. Sums along such paths are tracked as bitsets, each
+// one only over the sums that can still end on the target given the most the
+// rest of the path is able to add.
+struct DeletionPlanner {
+    dirs: Vec<NodeId>,
+    sizes: Vec<usize>,
+    ends: Vec<usize>,
+}
+
+impl DeletionPlanner {
+    fn can_take(&self, i: usize, end: usize, limit: usize) -> bool {
+        self.ends[i] <= end && self.sizes[i] <= limit
+    }
+
+    // Largest sum of a path from `start` to every position up to `end`, indexed from `start`
+    fn most_before(&self, start: usize, end: usize, limit: usize) -> Vec<usize> {
+        let mut most = vec![0; end - start + 1];
+        for i in start..end {
+            most[i + 1 - start] = most[i + 1 - start].max(most[i - start]);
+            if self.can_take(i, end, limit) {
+                let after = self.ends[i] - start;
+                most[after] = most[after].max(most[i - start] + self.sizes[i]);
             }
+        }
+        most
+    }
+
+    // Largest sum of a path from every position from `start` on to `end`, indexed from `start`
+    fn most_after(&self, start: usize, end: usize, limit: usize) -> Vec<usize> {
+        let mut most = vec![0; end - start + 1];
+        for i in (start..end).rev() {
+            most[i - start] = most[i + 1 - start];
+            if self.can_take(i, end, limit) {
+                most[i - start] = most[i - start].max(self.sizes[i] + most[self.ends[i] - start]);
+            }
+        }
+        most
+    }
 
-            // Handle going in
-            if let Some(dir) = line.strip_prefix("$ cd ") {
-                let key = current_node.to_string() + "_" + dir;
-                let exists = self.filemap.contains_key(&key);
-                if !exists {
-                    self.filemap.get_mut(&current_node).unwrap().children.push(key.to_string());
-                    self.filemap.insert(key.to_string(), NodeData::new_dir(Some(current_node.to_string())));
+    // Sums reaching `mid` from `start`, plus those reaching every directory whose
+    // subtree spans `mid` without going past `end`. Only the sums in `window`
+    // of a position are kept.
+    fn forward(&self, start: usize, mid: usize, end: usize, limit: usize, window: impl Fn(usize) -> (usize, usize)) -> (SumSet, Vec<(usize, SumSet)>) {
+        let mut pending = HashMap::from([(start, SumSet::zero(window(start)))]);
+        let mut crossing = Vec::new();
+        for i in start..mid {
+            let sums = pending.remove(&i).unwrap();
+            pending.entry(i + 1).or_insert_with(|| SumSet::empty(window(i + 1))).add_shifted(&sums, 0);
+            if self.can_take(i, end, limit) {
+                if self.ends[i] > mid {
+                    crossing.push((i, sums));
+                } else {
+                    let next = pending.entry(self.ends[i]).or_insert_with(|| SumSet::empty(window(self.ends[i])));
+                    next.add_shifted(&sums, self.sizes[i]);
                 }
-                current_node = key;
-                continue;
             }
+        }
+        (pending.remove(&mid).unwrap(), crossing)
+    }
 
-            // Discard ls - any line without $ is done after ls anyway
-            if line.eq("$ ls") {
-                continue;
+    // Sums reaching `end` from `mid` and from each of `keep`, only holding on to
+    // the positions that are still going to be read
+    fn backward(&self, mid: usize, end: usize, limit: usize, keep: &[usize], window: impl Fn(usize) -> (usize, usize)) -> HashMap<usize, SumSet> {
+        let mut uses: HashMap<usize, usize> = HashMap::new();
+        for i in mid..end {
+            *uses.entry(i + 1).or_default() += 1;
+            if self.can_take(i, end, limit) {
+                *uses.entry(self.ends[i]).or_default() += 1;
             }
+        }
+        keep.iter().chain([&mid]).for_each(|p| *uses.entry(*p).or_default() += 1);
 
-            // Handle contents of ls
-            let (info, name) = line.split_once(' ').unwrap();
-            let key = current_node.to_string() + "_" + name;
-            if info.eq("dir") { // Handle dir
-                if !self.filemap.contains_key(&key.to_string()) {
-                    self.filemap.get_mut(&current_node).unwrap().children.push(key.to_string());
-                    self.filemap.insert(key.to_string(), NodeData::new_dir(Some(current_node.to_string())));
-                }
-            } else { // Handle file
-                if !self.filemap.contains_key(&key.to_string()) {
-                    self.filemap.get_mut(&current_node).unwrap().children.push(key.to_string());
-                    self.filemap.insert(key.to_string(), NodeData::new_file(Some(current_node.to_string()), info.parse().unwrap()));
+        let mut sums = HashMap::from([(end, SumSet::zero(window(end)))]);
+        let mut release = |sums: &mut HashMap<usize, SumSet>, pos: usize| {
+            let left = uses.get_mut(&pos).unwrap();
+            *left -= 1;
+            if *left == 0 {
+                sums.remove(&pos);
+            }
+        };
+        for i in (mid..end).rev() {
+            let mut cur = SumSet::empty(window(i));
+            cur.add_shifted(&sums[&(i + 1)], 0);
+            release(&mut sums, i + 1);
+            if self.can_take(i, end, limit) {
+                cur.add_shifted(&sums[&self.ends[i]], self.sizes[i]);
+                release(&mut sums, self.ends[i]);
+            }
+            sums.insert(i, cur);
+        }
+        sums
+    }
+
+    // Directories to delete on a path from `start` to `end` freeing exactly `total`.
+    // Splits at the middle position like Hirschberg's alignment, so only the
+    // bitsets along the current split are ever kept around.
+    fn find(&self, start: usize, end: usize, total: usize) -> Vec<usize> {
+        if start == end || total == 0 {
+            return Vec::new();
+        }
+        if end - start == 1 {
+            return vec![start];
+        }
+
+        let mid = (start + end) / 2;
+        let (before, after) = (self.most_before(start, end, total), self.most_after(start, end, total));
+        let (reach_mid, crossing) =
+            self.forward(start, mid, end, total, |p| (total.saturating_sub(after[p - start]), total.min(before[p - start])));
+        let keep: Vec<usize> = crossing.iter().map(|(i, _)| self.ends[*i]).collect();
+        let sums = self.backward(mid, end, total, &keep, |p| (total.saturating_sub(before[p - start]), total.min(after[p - start])));
+
+        if let Some(a) = reach_mid.iter().find(|a| *a <= total && sums[&mid].contains(total - a)) {
+            let mut plan = self.find(start, mid, a);
+            plan.extend(self.find(mid, end, total - a));
+            return plan;
+        }
+        for (i, reach) in crossing.iter() {
+            let (size, after) = (self.sizes[*i], self.ends[*i]);
+            if let Some(a) = reach.iter().find(|a| a + size <= total && sums[&after].contains(total - a - size)) {
+                let mut plan = self.find(start, *i, a);
+                plan.push(*i);
+                plan.extend(self.find(after, end, total - a - size));
+                return plan;
+            }
+        }
+        unreachable!("{} bytes cannot be freed between {} and {}", total, start, end)
+    }
+}
+
+pub struct Filesystem {
+    nodes: Vec<Node>,
+}
+
+impl Filesystem {
+    pub fn from_transcript(lines: &[String]) -> Result<Self, FsError> {
+        let mut fs = Filesystem {
+            nodes: vec![Node { name: String::new(), parent: None, children: Vec::new(), is_dir: true, size: 0 }],
+        };
+        let mut cwd = ROOT;
+        for (idx, line) in lines.iter().enumerate() {
+            let err = |kind| FsError { line: idx + 1, kind };
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(cmd) = line.strip_prefix("$ ") {
+                match cmd.split_once(' ') {
+                    Some(("cd", path)) => cwd = fs.change_dir(cwd, path).map_err(err)?,
+                    None if cmd == "ls" => (),
+                    _ => return Err(err(FsErrorKind::UnknownCommand(cmd.to_string()))),
                 }
+                continue;
             }
+
+            match line.split_once(' ') {
+                Some(("dir", name)) => fs.add(cwd, name, true, 0).map_err(err)?,
+                Some((size, name)) => {
+                    let size = size.parse().map_err(|_| err(FsErrorKind::MalformedEntry(line.to_string())))?;
+                    fs.add(cwd, name, false, size).map_err(err)?
+                }
+                None => return Err(err(FsErrorKind::MalformedEntry(line.to_string()))),
+            };
+        }
+
+        // Children are always created after their parent, so walking the arena
+        // backwards sees every directory complete before it is added upwards
+        for id in (1..fs.nodes.len()).rev() {
+            let (size, parent) = (fs.nodes[id].size, fs.nodes[id].parent.unwrap());
+            fs.nodes[parent].size += size;
+        }
+        Ok(fs)
+    }
+
+    // Absolute paths start from the root, `..` stops at the root, unseen directories are created
+    fn change_dir(&mut self, cwd: NodeId, path: &str) -> Result<NodeId, FsErrorKind> {
+        let mut dir = if path.starts_with('/') { ROOT } else { cwd };
+        for part in path.split('/').filter(|p| !p.is_empty() && *p != ".") {
+            dir = match part {
+                ".." => self.nodes[dir].parent.unwrap_or(ROOT),
+                name => self.add(dir, name, true, 0)?,
+            };
         }
+        Ok(dir)
     }
 
-    fn print_tree(&self, root: &String, indent: usize) {
-        for child in self.filemap.get(root).unwrap().children.iter() {
-            let child_node = self.filemap.get(child).unwrap();
-            println!("{}{} [{}]", "\t".repeat(indent), child, child_node.size);
-            if let NodeType::Dir = child_node.node_type {
-                self.print_tree(&child, indent + 1);
+    // Listing the same entry again is fine as long as it agrees with what was seen before
+    fn add(&mut self, parent: NodeId, name: &str, is_dir: bool, size: usize) -> Result<NodeId, FsErrorKind> {
+        if let Some(id) = self.child(parent, name) {
+            let node = &self.nodes[id];
+            if node.is_dir != is_dir {
+                return Err(FsErrorKind::KindConflict { path: self.path(id) });
             }
+            if !is_dir && node.size != size {
+                return Err(FsErrorKind::SizeConflict { path: self.path(id), old: node.size, new: size });
+            }
+            return Ok(id);
+        }
+
+        let id = self.nodes.len();
+        self.nodes.push(Node { name: name.to_string(), parent: Some(parent), children: Vec::new(), is_dir, size });
+        self.nodes[parent].children.push(id);
+        Ok(id)
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id]
+    }
+
+    pub fn child(&self, parent: NodeId, name: &str) -> Option<NodeId> {
+        self.nodes[parent].children.iter().copied().find(|c| self.nodes[*c].name == name)
+    }
+
+    pub fn size(&self, id: NodeId) -> usize {
+        self.nodes[id].size
+    }
+
+    pub fn used_space(&self) -> usize {
+        self.size(ROOT)
+    }
+
+    pub fn path(&self, id: NodeId) -> String {
+        let mut parts = Vec::new();
+        let mut cur = id;
+        while let Some(parent) = self.nodes[cur].parent {
+            parts.push(self.nodes[cur].name.as_str());
+            cur = parent;
         }
+        parts.reverse();
+        format!("/{}", parts.join("/"))
+    }
+
+    pub fn lookup(&self, path: &str) -> Option<NodeId> {
+        path.split('/').filter(|p| !p.is_empty()).try_fold(ROOT, |dir, name| self.child(dir, name))
+    }
+
+    pub fn dirs(&self) -> impl Iterator<Item = NodeId> + '_ {
+        (0..self.nodes.len()).filter(|id| self.nodes[*id].is_dir)
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = NodeId> + '_ {
+        (0..self.nodes.len()).filter(|id| !self.nodes[*id].is_dir)
+    }
+
+    pub fn dir_sizes(&self) -> Vec<(String, usize)> {
+        self.dirs().map(|id| (self.path(id), self.size(id))).collect()
+    }
+
+    // Biggest first, equal sizes in path order
+    pub fn largest_dirs(&self, n: usize) -> Vec<NodeId> {
+        let mut dirs: Vec<_> = self.dirs().map(|id| (std::cmp::Reverse(self.size(id)), self.path(id), id)).collect();
+        dirs.sort();
+        dirs.into_iter().take(n).map(|(_, _, id)| id).collect()
+    }
+
+    pub fn dirs_in_range(&self, range: impl RangeBounds<usize>) -> Vec<NodeId> {
+        self.dirs().filter(|id| range.contains(&self.size(*id))).collect()
+    }
+
+    // Patterns with a '/' are matched against the whole path, others only against the file name
+    pub fn find_files(&self, pattern: &str) -> Vec<NodeId> {
+        self.files()
+            .filter(|id| match pattern.contains('/') {
+                true => glob_match(pattern, &self.path(*id)),
+                false => glob_match(pattern, &self.nodes[*id].name),
+            })
+            .collect()
+    }
+
+    pub fn smallest_dir_to_delete(&self, disk: Disk) -> Option<NodeId> {
+        let to_free = disk.space_to_free(self);
+        self.dirs().filter(|id| self.size(*id) >= to_free).min_by_key(|id| self.size(*id))
     }
 
-    fn calc_size(&mut self, root: &String) -> usize {
-        let node = self.filemap.get(root).unwrap();
-        if let NodeType::File = node.node_type {
-            return node.size;
+    // Fewest bytes freed by deleting directories none of which contains another,
+    // or None when even deleting everything is not enough
+    pub fn plan_deletion(&self, disk: Disk) -> Option<DeletionPlan> {
+        let to_free = disk.space_to_free(self);
+        if to_free == 0 {
+            return Some(DeletionPlan::default());
         }
 
-        let mut total_size = 0;
-        let childs: Vec<String> = self.filemap.get(root).unwrap().children.iter().cloned().collect();
-        for child in childs.iter() {
-            total_size += self.calc_size(child);
+        // No plan has to free more than the best single directory does, and
+        // when that one frees just enough nothing else can do better
+        let single = self.smallest_dir_to_delete(disk)?;
+        let best = self.size(single);
+        if best == to_free {
+            return Some(DeletionPlan { dirs: vec![single], freed: best });
         }
 
-        self.filemap.get_mut(root).unwrap().size = total_size;
-        total_size
+        let mut planner = DeletionPlanner { dirs: Vec::new(), sizes: Vec::new(), ends: Vec::new() };
+        self.visit_dirs(ROOT, &mut planner);
+        let n = planner.dirs.len();
+        let limit = best - 1;
+        let (before, after) = (planner.most_before(0, n, limit), planner.most_after(0, n, limit));
+        // Nothing smaller than the single directory can free enough, e.g. when that is the root
+        if after[0] < to_free {
+            return Some(DeletionPlan { dirs: vec![single], freed: best });
+        }
+
+        let sums = planner.backward(0, n, limit, &[], |p| (to_free.saturating_sub(before[p]), limit.min(after[p])));
+        let Some(freed) = sums[&0].iter().find(|s| *s >= to_free) else {
+            return Some(DeletionPlan { dirs: vec![single], freed: best });
+        };
+        let dirs = planner.find(0, n, freed).into_iter().map(|i| planner.dirs[i]).collect();
+        Some(DeletionPlan { dirs, freed })
+    }
+
+    fn visit_dirs(&self, id: NodeId, planner: &mut DeletionPlanner) {
+        let pos = planner.dirs.len();
+        planner.dirs.push(id);
+        planner.sizes.push(self.size(id));
+        planner.ends.push(0);
+        for child in self.nodes[id].children.iter().filter(|c| self.nodes[**c].is_dir) {
+            self.visit_dirs(*child, planner);
+        }
+        planner.ends[pos] = planner.dirs.len();
     }
 
+    fn sorted_children(&self, id: NodeId) -> Vec<NodeId> {
+        let mut children = self.nodes[id].children.clone();
+        children.sort_by(|a, b| self.nodes[*a].name.cmp(&self.nodes[*b].name));
+        children
+    }
+
+    // Like `du -h`, every directory after its subdirectories
+    pub fn render_du(&self) -> String {
+        let mut lines = Vec::new();
+        self.du_lines(ROOT, &mut lines);
+        lines.join("\n")
+    }
+
+    fn du_lines(&self, id: NodeId, lines: &mut Vec<String>) {
+        for child in self.sorted_children(id).into_iter().filter(|c| self.nodes[*c].is_dir) {
+            self.du_lines(child, lines);
+        }
+        lines.push(format!("{}\t{}", human_size(self.size(id)), self.path(id)));
+    }
+
+    // Like `tree`, with sizes in brackets
+    pub fn render_tree(&self) -> String {
+        let mut lines = vec![format!("/ ({})", self.used_space())];
+        self.tree_lines(ROOT, "", &mut lines);
+        lines.join("\n")
+    }
+
+    fn tree_lines(&self, id: NodeId, prefix: &str, lines: &mut Vec<String>) {
+        let children = self.sorted_children(id);
+        for (idx, child) in children.iter().enumerate() {
+            let last = idx + 1 == children.len();
+            let node = &self.nodes[*child];
+            let slash = if node.is_dir { "/" } else { "" };
+            lines.push(format!("{}{}{}{} ({})", prefix, if last { "└── " } else { "├── " }, node.name, slash, node.size));
+            if node.is_dir {
+                self.tree_lines(*child, &format!("{}{}", prefix, if last { "    " } else { "│   " }), lines);
+            }
+        }
+    }
 }
 
-fn part1(input: &InputType) -> String {
-    let mut fs = Filesystem::new();
-    fs.build(input);
-    fs.calc_size(&"_/".to_string());
-    // fs.print_tree(&"".to_string(), 0);
+// Sizes in powers of 1024 with one decimal below 10, rounded up as `du -h` does
+pub fn human_size(size: usize) -> String {
+    const UNITS: [&str; 5] = ["", "K", "M", "G", "T"];
+    let mut value = size as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < 4 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        return size.to_string();
+    }
+
+    let mut value = if value < 10.0 { (value * 10.0).ceil() / 10.0 } else { value.ceil() };
+    if value >= 1024.0 && unit < 4 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    match value < 10.0 {
+        true => format!("{:.1}{}", value, UNITS[unit]),
+        false => format!("{:.0}{}", value, UNITS[unit]),
+    }
+}
+
+// `?` matches one character and `*` any run of them, except that only `**` reaches across '/'
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    fn matches(pattern: &[char], text: &[char]) -> bool {
+        match pattern {
+            [] => text.is_empty(),
+            ['*', '*', rest @ ..] => (0..=text.len()).any(|i| matches(rest, &text[i..])),
+            ['*', rest @ ..] => {
+                let run = text.iter().take_while(|c| **c != '/').count();
+                (0..=run).any(|i| matches(rest, &text[i..]))
+            }
+            ['?', rest @ ..] => text.first().is_some_and(|c| *c != '/') && matches(rest, &text[1..]),
+            [p, rest @ ..] => text.first() == Some(p) && matches(rest, &text[1..]),
+        }
+    }
+
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    matches(&pattern, &text)
+}
 
-    fs.filemap.iter()
-    .filter(|(_, v)| v.node_type == NodeType::Dir)
-    .map(|(_, v)| v.size)
-    .filter(|s| s <= &100_000)
-    .sum::<usize>()
-    .to_string()
+fn part1(input: &InputType) -> String {
+    input.dirs_in_range(..=100_000).iter().map(|id| input.size(*id)).sum::<usize>().to_string()
 }
 
 fn part2(input: &InputType) -> String {
-    let mut fs = Filesystem::new();
-    fs.build(input);
-    fs.calc_size(&"_/".to_string());
-
-    let total_size = 70000000;
-    let needed_size = 30000000;
-    let used_size = fs.filemap.get("_/").unwrap().size;
-    let curr_free_size = total_size - used_size;
-    let need_to_free_size = needed_size - curr_free_size;
-
-    fs.filemap.iter()
-    .filter(|(_, v)| v.node_type == NodeType::Dir)
-    .map(|(_, v)| v.size)
-    .filter(|s| s >= &need_to_free_size)
-    .sorted()
-    .take(1)
-    .exactly_one()
-    .unwrap()
-    .to_string()
-}
-
-type InputType = Vec<String>;
+    let dir = input.smallest_dir_to_delete(Disk::PUZZLE).unwrap();
+    input.size(dir).to_string()
+}
+
+type InputType = Filesystem;
 fn parse_input(raw_input: &[String]) -> InputType {
-    raw_input.iter().map(|x| x.parse().unwrap()).collect()
+    Filesystem::from_transcript(raw_input).unwrap()
 }
 
 pub fn solve(raw_input: &[String]) -> Solution {
@@ -169,3 +546,100 @@ pub fn solve(raw_input: &[String]) -> Solution {
     let elapsed = now.elapsed();
     (solution, elapsed)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // xorshift64, enough for random trees without pulling in a crate
+    fn next_random(seed: &mut u64) -> u64 {
+        *seed ^= *seed << 13;
+        *seed ^= *seed >> 7;
+        *seed ^= *seed << 17;
+        *seed
+    }
+
+    fn random_listing(seed: &mut u64, depth: usize, transcript: &mut Vec<String>) {
+        transcript.push("$ ls".to_string());
+        let subdirs = if depth < 3 { next_random(seed) % 4 } else { 0 };
+        for d in 0..subdirs {
+            transcript.push(format!("dir d{}", d));
+        }
+        for f in 0..next_random(seed) % 3 {
+            transcript.push(format!("{} f{}", next_random(seed) % 1000 + 1, f));
+        }
+        for d in 0..subdirs {
+            transcript.push(format!("$ cd d{}", d));
+            random_listing(seed, depth + 1, transcript);
+            transcript.push("$ cd ..".to_string());
+        }
+    }
+
+    fn contains(fs: &Filesystem, dir: NodeId, mut other: NodeId) -> bool {
+        while let Some(parent) = fs.nodes[other].parent {
+            if parent == dir {
+                return true;
+            }
+            other = parent;
+        }
+        false
+    }
+
+    // Tries every set of directories, fine for the few a random tree has
+    fn brute_force_plan(fs: &Filesystem, to_free: usize) -> Option<usize> {
+        let dirs: Vec<NodeId> = fs.dirs().collect();
+        (0..1u32 << dirs.len())
+            .filter_map(|mask| {
+                let chosen: Vec<NodeId> = (0..dirs.len()).filter(|i| mask >> i & 1 == 1).map(|i| dirs[i]).collect();
+                let nested = chosen.iter().any(|a| chosen.iter().any(|b| contains(fs, *a, *b)));
+                let freed = chosen.iter().map(|id| fs.size(*id)).sum::<usize>();
+                (!nested && freed >= to_free).then_some(freed)
+            })
+            .min()
+    }
+
+    #[test]
+    fn deletion_plan_matches_brute_force() {
+        let mut seed = 0x2022_0707;
+        let mut trees = 0;
+        while trees < 200 {
+            let mut transcript = vec!["$ cd /".to_string()];
+            random_listing(&mut seed, 0, &mut transcript);
+            let fs = Filesystem::from_transcript(&transcript).unwrap();
+            let dirs = fs.dirs().count();
+            if dirs > 16 || fs.used_space() == 0 {
+                continue;
+            }
+            trees += 1;
+
+            let used = fs.used_space();
+            let disk = Disk { capacity: used, required: next_random(&mut seed) as usize % used + 1 };
+            let plan = fs.plan_deletion(disk).unwrap();
+            assert_eq!(Some(plan.freed), brute_force_plan(&fs, disk.required), "{:?}", transcript);
+            assert_eq!(plan.dirs.iter().map(|id| fs.size(*id)).sum::<usize>(), plan.freed);
+            assert!(plan.dirs.iter().all(|a| plan.dirs.iter().all(|b| !contains(&fs, *a, *b))));
+        }
+    }
+
+    #[test]
+    fn deletion_plan_returns_early_without_a_choice() {
+        let transcript: Vec<String> = ["$ cd /", "$ ls", "dir a", "10 f", "$ cd a", "$ ls", "20 g"].iter().map(|l| l.to_string()).collect();
+        let fs = Filesystem::from_transcript(&transcript).unwrap();
+        let root_only = fs.plan_deletion(Disk { capacity: 1, required: 1 }).unwrap();
+        assert_eq!(root_only, DeletionPlan { dirs: vec![ROOT], freed: 30 });
+        let exact = fs.plan_deletion(Disk { capacity: 30, required: 20 }).unwrap();
+        assert_eq!(exact, DeletionPlan { dirs: vec![fs.lookup("/a").unwrap()], freed: 20 });
+        assert_eq!(fs.plan_deletion(Disk { capacity: 30, required: 31 }), None);
+    }
+
+    #[test]
+    fn human_size_rounds_up_like_du() {
+        assert_eq!(human_size(1023), "1023");
+        assert_eq!(human_size(1024), "1.0K");
+        assert_eq!(human_size(1025), "1.1K");
+        assert_eq!(human_size(10 * 1024 - 1), "10K");
+        assert_eq!(human_size(10 * 1024 + 1), "11K");
+        assert_eq!(human_size(1024 * 1024 - 1), "1.0M");
+        assert_eq!(human_size(14_848_514), "15M");
+    }
+}
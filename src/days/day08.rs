@@ -1,233 +1,204 @@
-use std::{collections::HashSet, hash::Hash, cmp::max};
+use std::{io, path::Path};
 
 use crate::common::Solution;
-
-#[derive(Copy, Clone, Hash, PartialEq, Eq)]
-pub struct Point {
-    x: usize,
-    y: usize,
+use crate::grid::Grid;
+use crate::points::Point2;
+use crate::render::{Image, Rgb};
+
+const SCORE_RAMP: &[u8] = b" .:-=+*#%@";
+const BEST_COLOR: Rgb = [0, 255, 255];
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Side {
+    Left = 0,
+    Right,
+    Top,
+    Bottom,
 }
 
-impl Point {
-    fn new(x: usize, y: usize) -> Self {
-        Self { x, y }
+impl Side {
+    pub const ALL: [Side; 4] = [Side::Left, Side::Right, Side::Top, Side::Bottom];
+
+    pub fn mask(&self) -> u8 {
+        1 << *self as u8
     }
 }
 
-struct TreeSearcher {
-    visible: HashSet<Point>
+// Sides the tree can be seen from, and how far it sees towards each of them
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+pub struct TreeView {
+    pub visible_from: u8,
+    pub distances: [usize; 4],
 }
 
-impl TreeSearcher {
-    fn new() -> Self {
-        Self { visible: HashSet::new() }
-    }
-
-    fn find_visible(&mut self, input: &Vec<Vec<u8>>) {
-        let mut max_height;
-        let row_size = input.get(0).unwrap().len();
-        let col_size = input.len();
-
-        // iterate each row 2 times
-        for (y, row) in input.iter().enumerate().skip(1).take(col_size - 2) {
-            max_height = 0;
-            for (x, height) in row.iter().enumerate() {
-                if height > &max_height {
-                    // println!("Tree [{}, {}] with height {} visible, max height {}", x, y, height, max_height);
-                    max_height = *height;
-                    if x != 0 && x != (row_size - 1) {
-                        self.visible.insert(Point::new(x, y));
-                    }
-                }
-                if max_height == 9 {
-                    break;
-                }
-            }
-            max_height = 0;
-            for (x, height) in row.iter().enumerate().rev() {
-                if height > &max_height {
-                    // println!("Tree [{}, {}] with height {} visible, max height {}", x, y, height, max_height);
-                    max_height = *height;
-                    if x != 0 && x != (row_size - 1) {
-                        self.visible.insert(Point::new(x, y));
-                    }
-                }
-                if max_height == 9 {
-                    break;
-                }
-            }
-        }
-
-        for x in 1..(row_size - 2) {
-            max_height = 0;
-            for (y, row) in input.iter().enumerate() {
-                let height = row.get(x).unwrap();
-                if height > &max_height {
-                    // println!("Tree [{}, {}] with height {} visible, max height {}", x, y, height, max_height);
-                    max_height = *height;
-                    if y != 0 && y != (col_size - 1) {
-                        self.visible.insert(Point::new(x, y));
-                    }
-                }
-                if max_height == 9 {
-                    break;
-                }
-            }
-            max_height = 0;
-            for (y, row) in input.iter().enumerate().rev() {
-                let height = row.get(x).unwrap();
-                if height > &max_height {
-                    // println!("Tree [{}, {}] with height {} visible, max height {}", x, y, height, max_height);
-                    max_height = *height;
-                    if y != 0 && y != (col_size - 1) {
-                        self.visible.insert(Point::new(x, y));
-                    }
-                }
-                if max_height == 9 {
-                    break;
-                }
-            }
-        }
+impl TreeView {
+    pub fn is_visible(&self) -> bool {
+        self.visible_from != 0
     }
 
-    fn print_finds(&self, input: &InputType) {
-        let row_size = input.get(0).unwrap().len();
-        let col_size = input.len();
-
-        let mut visible_print = vec![vec![' '; row_size]; col_size];
-        for Point{x, y} in self.visible.iter() {
-            *visible_print.get_mut(*y).unwrap().get_mut(*x).unwrap() = char::from_digit(*input.get(*y).unwrap().get(*x).unwrap() as u32, 10).unwrap();
-        }
-
-        for r in visible_print.iter() {
-            for c in r.iter() {
-                print!("{} ", c);
-            }
-            println!("");
-        }
+    pub fn is_visible_from(&self, side: Side) -> bool {
+        self.visible_from & side.mask() != 0
     }
 
-    fn get_val(x: usize, y: usize, input: &Vec<Vec<u8>>) -> u8 {
-        *input.get(y).unwrap().get(x).unwrap()
+    pub fn distance(&self, side: Side) -> usize {
+        self.distances[side as usize]
     }
 
-    fn get_scenic_score(x: usize, y: usize, input: &Vec<Vec<u8>>) -> usize {
-        let mut total_score = 1;
-        let row_size = input.get(0).unwrap().len() as i32;
-        let col_size = input.len() as i32;
-        let curr_height = Self::get_val(x, y, input);
+    pub fn scenic_score(&self) -> usize {
+        self.distances.iter().product()
+    }
+}
 
-        let mut xx = x as i32;
-        let yy = y as i32;
-        let mut score = 0;
-        loop {
-            xx -= 1;
-            if xx < 0 || yy < 0 || xx == row_size || yy == col_size {
-                break;
-            }
+pub struct TreeSearcher {
+    views: Grid<TreeView>,
+}
 
-            let height = Self::get_val(xx as usize, yy as usize, input);
-            score += 1;
-            if height >= curr_height {
-                break;
-            }
+impl TreeSearcher {
+    pub fn new(input: &Grid<u8>) -> Self {
+        let mut views = input.map(|_| TreeView::default());
+        let (rows, cols) = (input.rows() as i32, input.cols() as i32);
+        for y in 0..rows {
+            Self::sweep(input, &mut views, Side::Left, (0..cols).map(|x| Point2::xy(x, y)));
+            Self::sweep(input, &mut views, Side::Right, (0..cols).rev().map(|x| Point2::xy(x, y)));
         }
-
-        if score == 0 {
-            return 0;
+        for x in 0..cols {
+            Self::sweep(input, &mut views, Side::Top, (0..rows).map(|y| Point2::xy(x, y)));
+            Self::sweep(input, &mut views, Side::Bottom, (0..rows).rev().map(|y| Point2::xy(x, y)));
         }
-        total_score *= score;
-        score = 0;
-        let mut xx = x as i32;
-        loop {
-            xx += 1;
-            if xx < 0 || yy < 0 || xx == row_size || yy == col_size {
-                break;
+        Self { views }
+    }
+
+    // Walks one line starting at `side`. The stack keeps the trees that can still
+    // block the view of later ones, non-increasing in height from the bottom since
+    // equal heights stay, so each tree is pushed and popped once.
+    fn sweep(input: &Grid<u8>, views: &mut Grid<TreeView>, side: Side, line: impl Iterator<Item = Point2>) {
+        let mut stack: Vec<(usize, u8)> = Vec::new();
+        for (i, pos) in line.enumerate() {
+            let height = input[pos];
+            while stack.last().is_some_and(|(_, h)| *h < height) {
+                stack.pop();
             }
 
-            let height = Self::get_val(xx as usize, yy as usize, input);
-            score += 1;
-            if height >= curr_height {
-                break;
+            let view = &mut views[pos];
+            match stack.last() {
+                Some((blocker, _)) => view.distances[side as usize] = i - blocker,
+                None => {
+                    view.distances[side as usize] = i;
+                    view.visible_from |= side.mask();
+                }
             }
+            stack.push((i, height));
         }
+    }
 
+    pub fn views(&self) -> &Grid<TreeView> {
+        &self.views
+    }
 
-        if score == 0 {
-            return 0;
-        }
-        total_score *= score;
-        score = 0;
-        let xx = x as i32;
-        let mut yy = y as i32;
-        loop {
-            yy -= 1;
-            if xx < 0 || yy < 0 || xx == row_size || yy == col_size {
-                break;
-            }
+    pub fn count_visible(&self) -> usize {
+        self.views.iter().filter(|(_, v)| v.is_visible()).count()
+    }
 
-            let height = Self::get_val(xx as usize, yy as usize, input);
-            score += 1;
-            if height >= curr_height {
-                break;
-            }
-        }
+    pub fn best_tree(&self) -> Option<(Point2, usize)> {
+        self.views
+            .iter()
+            .map(|(pos, v)| (pos, v.scenic_score()))
+            .max_by_key(|(_, score)| *score)
+    }
 
+    pub fn get_max_scenic_score(&self) -> usize {
+        self.best_tree().map_or(0, |(_, score)| score)
+    }
 
-        if score == 0 {
-            return 0;
-        }
-        total_score *= score;
-        score = 0;
-        let mut yy = y as i32;
-        loop {
-            yy += 1;
-            if xx < 0 || yy < 0 || xx == row_size || yy == col_size {
-                break;
-            }
+    // Scores in 0..=1 on a log scale, the best ones are orders of magnitude above the rest
+    fn score_levels(&self) -> Grid<f64> {
+        let max = (self.get_max_scenic_score() as f64).ln_1p();
+        self.views.map(|v| if max == 0.0 { 0.0 } else { (v.scenic_score() as f64).ln_1p() / max })
+    }
 
-            let height = Self::get_val(xx as usize, yy as usize, input);
-            score += 1;
-            if height >= curr_height {
-                break;
+    // Each tree as the hex digit of its side mask (left = 1, right = 2, top = 4, bottom = 8), '.' if hidden
+    pub fn visibility_map(&self) -> String {
+        self.views.render(|v| match v.visible_from {
+            0 => '.',
+            mask => char::from_digit(mask as u32, 16).unwrap(),
+        })
+    }
+
+    pub fn score_map(&self) -> String {
+        self.score_levels()
+            .render(|level| SCORE_RAMP[(level * (SCORE_RAMP.len() - 1) as f64).round() as usize] as char)
+    }
+
+    // Hidden trees stay dark, visible ones get brighter with every side they can be seen from
+    pub fn visibility_image(&self, scale: usize) -> Image {
+        Image::from_grid(&self.views, scale, |v| {
+            let sides = v.visible_from.count_ones() as u8;
+            if sides == 0 {
+                [0, 40, 0]
+            } else {
+                [0, 80 + 40 * sides, 0]
             }
+        })
+    }
+
+    // Black through red and yellow to white, with the best tree marked
+    pub fn score_image(&self, scale: usize) -> Image {
+        let mut image = Image::from_grid(&self.score_levels(), scale, |level| {
+            let heat = (level * 765.0).round() as usize;
+            [heat.min(255) as u8, heat.saturating_sub(255).min(255) as u8, heat.saturating_sub(510) as u8]
+        });
+        if let Some((best, _)) = self.best_tree() {
+            image.fill_cell(best.x() as usize, best.y() as usize, scale, BEST_COLOR);
         }
+        image
+    }
 
-        total_score * score
+    // Writes visibility.<ext> and scenic.<ext> into dir
+    pub fn save_images(&self, dir: &Path, scale: usize, ext: &str) -> io::Result<()> {
+        self.visibility_image(scale).save(&dir.join(format!("visibility.{}", ext)))?;
+        self.score_image(scale).save(&dir.join(format!("scenic.{}", ext)))
     }
 
-    fn get_max_scenic_score(input: &Vec<Vec<u8>>) -> usize {
-        let row_size = input.get(0).unwrap().len();
-        let col_size = input.len();
-        let mut max_score = 0;
+    // Walks every direction from every tree, kept as a reference for the sweeps
+    pub fn brute_force(input: &Grid<u8>) -> Grid<TreeView> {
+        let steps = [Point2::xy(-1, 0), Point2::xy(1, 0), Point2::xy(0, -1), Point2::xy(0, 1)];
+        let mut views = input.map(|_| TreeView::default());
+        for pos in input.positions() {
+            let height = input[pos];
+            for side in Side::ALL {
+                let mut next = pos + steps[side as usize];
+                let mut distance = 0;
+                let mut blocked = false;
+                while let Some(h) = input.get(&next) {
+                    distance += 1;
+                    if *h >= height {
+                        blocked = true;
+                        break;
+                    }
+                    next += steps[side as usize];
+                }
 
-        for x in 1..(row_size - 1) {
-            for y in 1..(col_size - 1) {
-                let score = Self::get_scenic_score(x, y, input);
-                max_score = max(score, max_score);
+                views[pos].distances[side as usize] = distance;
+                if !blocked {
+                    views[pos].visible_from |= side.mask();
+                }
             }
         }
-
-        max_score
+        views
     }
 }
 
 fn part1(input: &InputType) -> String {
-    let mut ts = TreeSearcher::new();
-    ts.find_visible(input);
-    // ts.print_finds(input);
-    let row_size = input.get(0).unwrap().len();
-    let col_size = input.len();
-    (ts.visible.len() + row_size * 2 + col_size * 2 - 4).to_string()
+    TreeSearcher::new(input).count_visible().to_string()
 }
 
 fn part2(input: &InputType) -> String {
-    TreeSearcher::get_max_scenic_score(input).to_string()
+    TreeSearcher::new(input).get_max_scenic_score().to_string()
 }
 
-type InputType = Vec<Vec<u8>>;
+type InputType = Grid<u8>;
 fn parse_input(raw_input: &[String]) -> InputType {
-    raw_input.iter().map(|x| x.chars().map(|y| y.to_digit(10).unwrap() as u8).collect()).collect()
+    Grid::from_lines(raw_input, |c| c.to_digit(10).unwrap() as u8)
 }
 
 pub fn solve(raw_input: &[String]) -> Solution {
@@ -239,3 +210,31 @@ pub fn solve(raw_input: &[String]) -> Solution {
     let elapsed = now.elapsed();
     (solution, elapsed)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // xorshift64, enough to shuffle heights around without pulling in a crate
+    fn random_grid(seed: &mut u64, rows: usize, cols: usize) -> Grid<u8> {
+        let data = (0..rows * cols)
+            .map(|_| {
+                *seed ^= *seed << 13;
+                *seed ^= *seed >> 7;
+                *seed ^= *seed << 17;
+                (*seed % 10) as u8
+            })
+            .collect();
+        Grid::new(rows, cols, data)
+    }
+
+    #[test]
+    fn sweeps_match_brute_force() {
+        let mut seed = 0x2022_0808;
+        let sizes = [(1, 1), (1, 7), (7, 1), (2, 9), (9, 2), (5, 5), (13, 8)];
+        for (rows, cols) in sizes.iter().copied().cycle().take(sizes.len() * 20) {
+            let grid = random_grid(&mut seed, rows, cols);
+            assert_eq!(TreeSearcher::new(&grid).views(), &TreeSearcher::brute_force(&grid), "{}x{} grid", rows, cols);
+        }
+    }
+}
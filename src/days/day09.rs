@@ -1,173 +1,252 @@
-use std::{str::FromStr, collections::HashSet, fmt};
-
-use itertools::Itertools;
+use std::{collections::HashSet, fmt, fs, io, path::Path, str::FromStr};
 
 use crate::common::Solution;
-#[derive(Debug)]
-enum Direction {
-    Up(i32),
-    Down(i32),
-    Left(i32),
-    Right(i32),
-    Diag(i32, i32)
+use crate::points::Point2;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
 }
 
 impl Direction {
-    fn val(&self) -> i32 {
+    pub fn offset(&self) -> Point2 {
         match self {
-            Direction::Up(val) => *val,
-            Direction::Down(val) => *val,
-            Direction::Left(val) => *val,
-            Direction::Right(val) => *val,
-            _ => 0
+            Direction::Up => Point2::xy(0, 1),
+            Direction::Down => Point2::xy(0, -1),
+            Direction::Left => Point2::xy(-1, 0),
+            Direction::Right => Point2::xy(1, 0),
         }
     }
 }
 
-impl FromStr for Direction {
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Motion {
+    pub direction: Direction,
+    pub steps: usize,
+}
+
+impl FromStr for Motion {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if let Some((x, y)) = s.split_once(" ") {
-            let val = y.parse().unwrap();
-            return match x {
-                "U" => Ok(Direction::Up(val)),
-                "D" => Ok(Direction::Down(val)),
-                "L" => Ok(Direction::Left(val)),
-                "R" => Ok(Direction::Right(val)),
-                _ => Err(())
-            };
-        }
-        Err(())
+        let (dir, steps) = s.split_once(' ').ok_or(())?;
+        let direction = match dir {
+            "U" => Direction::Up,
+            "D" => Direction::Down,
+            "L" => Direction::Left,
+            "R" => Direction::Right,
+            _ => return Err(()),
+        };
+        let steps = steps.parse().map_err(|_| ())?;
+        Ok(Motion { direction, steps })
     }
 }
 
-#[derive(Copy, Clone, Hash, PartialEq, Eq, Debug)]
-struct Point {
-    x: i32,
-    y: i32,
-}
-
-impl fmt::Display for Point {
+impl fmt::Display for Motion {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "[{},{}]", self.x, self.y)
+        let dir = match self.direction {
+            Direction::Up => 'U',
+            Direction::Down => 'D',
+            Direction::Left => 'L',
+            Direction::Right => 'R',
+        };
+        write!(f, "{} {}", dir, self.steps)
     }
 }
 
-impl Point {
-    fn new(x: i32, y: i32) -> Self {
-        Self { x, y }
+// Unrolls the motions into the single steps the head takes
+pub fn single_steps(motions: &[Motion]) -> impl Iterator<Item = Direction> + '_ {
+    motions.iter().flat_map(|m| std::iter::repeat_n(m.direction, m.steps))
+}
+
+// Knot 0 is the head, the last one is the tail
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Rope {
+    knots: Vec<Point2>,
+}
+
+impl Rope {
+    pub fn new(knots: usize) -> Self {
+        assert!(knots > 0, "a rope needs at least a head");
+        Rope { knots: vec![Point2::origin(); knots] }
     }
 
-    fn is_in_line(&self, other: &Point) -> bool {
-        self.x == other.x || self.y == other.y
+    pub fn knots(&self) -> &[Point2] {
+        &self.knots
     }
 
-    fn make_move(&mut self, dir: &Direction) {
-        match dir {
-            Direction::Up(val) => self.y += 1,
-            Direction::Down(val) => self.y -= 1,
-            Direction::Left(val) => self.x -= 1,
-            Direction::Right(val) => self.x += 1,
-            Direction::Diag(x, y) => {self.x += x; self.y += y;}
-        }
+    pub fn head(&self) -> Point2 {
+        self.knots[0]
+    }
+
+    pub fn tail(&self) -> Point2 {
+        *self.knots.last().unwrap()
     }
 
-    fn is_too_far(&self, other: &Point) -> bool {
-        if self.is_in_line(other) {
-            (other.x - self.x + other.y - self.y).abs() > 1
-        } else {
-            (other.x - self.x).abs() + (other.y - self.y).abs() > 2
+    // Moves the head once, every knot then catches up with the one before it.
+    // A knot that stays put leaves the rest of the rope where it was.
+    pub fn step(&mut self, direction: Direction) {
+        self.knots[0] += direction.offset();
+        for idx in 1..self.knots.len() {
+            let diff = self.knots[idx - 1] - self.knots[idx];
+            if diff.chebyshev(&Point2::origin()) <= 1 {
+                break;
+            }
+            self.knots[idx] += diff.signum();
         }
     }
 
-    fn get_tail_move(head: &Point, tail: &Point) -> Option<Direction> {
-        if head == tail || !head.is_too_far(tail) {
-            return None;
+    pub fn apply(&mut self, motion: &Motion) {
+        (0..motion.steps).for_each(|_| self.step(motion.direction));
+    }
+
+    // Positions the given knot has been at, including where it started
+    pub fn visited(&mut self, motions: &[Motion], knot: usize) -> HashSet<Point2> {
+        let mut visited = HashSet::from([self.knots[knot]]);
+        for direction in single_steps(motions) {
+            self.step(direction);
+            visited.insert(self.knots[knot]);
         }
+        visited
+    }
 
-        // in line
-        if head.is_in_line(tail) {
-            if head.x > tail.x {
-                return Some(Direction::Right(1));
-            } else if head.x < tail.x {
-                return Some(Direction::Left(1));
-            } else if head.y > tail.y {
-                return Some(Direction::Up(1));
-            } else {
-                return Some(Direction::Down(1));
-            }
+    // The knot configuration before the first step and after every one
+    pub fn history(&mut self, motions: &[Motion]) -> Vec<Vec<Point2>> {
+        let mut history = vec![self.knots.clone()];
+        for direction in single_steps(motions) {
+            self.step(direction);
+            history.push(self.knots.clone());
         }
+        history
+    }
+}
 
-        // diagonal
-        let diag_x = if head.x > tail.x { 1 } else { -1 };
-        let diag_y = if head.y > tail.y { 1 } else { -1 };
-        Some(Direction::Diag(diag_x, diag_y))
+// Label drawn for a knot, the same as in the puzzle's illustrations
+fn knot_label(idx: usize, knots: usize) -> char {
+    match idx {
+        0 => 'H',
+        1 if knots == 2 => 'T',
+        _ => char::from_digit(idx as u32, 36).unwrap_or('*'),
     }
 }
 
+// Window of the plane that is drawn, y grows upwards like in the puzzle
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Viewport {
+    min: Point2,
+    width: i32,
+    height: i32,
+}
 
-fn part1(input: &InputType) -> String {
-    let mut head = Point::new(0, 0);
-    let mut tail = Point::new(0, 0);
-    let mut tail_moves = HashSet::new();
-    tail_moves.insert(tail);
-    for dir in input.iter() {
-        let dist = match dir {
-            Direction::Up(val) => val,
-            Direction::Down(val) => val,
-            Direction::Left(val) => val,
-            Direction::Right(val) => val,
-            _ => &0
-        };
-        for _ in 0..*dist {
-            head.make_move(dir);
-            if let Some(tail_dir) = Point::get_tail_move(&head, &tail) {
-                tail.make_move(&tail_dir);
-                tail_moves.insert(tail);
+impl Viewport {
+    // Starts centered on the origin
+    pub fn new(width: usize, height: usize) -> Self {
+        let (width, height) = (width.max(1) as i32, height.max(1) as i32);
+        Viewport { min: Point2::xy(-width / 2, -height / 2), width, height }
+    }
+
+    pub fn contains(&self, pos: &Point2) -> bool {
+        (self.min.x()..self.min.x() + self.width).contains(&pos.x())
+            && (self.min.y()..self.min.y() + self.height).contains(&pos.y())
+    }
+
+    // Scrolls as little as needed to keep every knot in view, or centers on the
+    // head when the rope does not fit at all
+    pub fn follow(&mut self, knots: &[Point2]) {
+        let lo = knots.iter().fold(knots[0], |acc, k| acc.component_min(k));
+        let hi = knots.iter().fold(knots[0], |acc, k| acc.component_max(k));
+        let size = [self.width, self.height];
+        for axis in 0..2 {
+            if hi[axis] - lo[axis] >= size[axis] {
+                self.min[axis] = knots[0][axis] - size[axis] / 2;
+            } else if lo[axis] < self.min[axis] {
+                self.min[axis] = lo[axis];
+            } else if hi[axis] >= self.min[axis] + size[axis] {
+                self.min[axis] = hi[axis] - size[axis] + 1;
             }
         }
+    }
 
+    // Knots cover each other with the lower index on top, then the start 's', then visited '#'
+    pub fn render(&self, knots: &[Point2], visited: &HashSet<Point2>) -> String {
+        (0..self.height)
+            .rev()
+            .map(|dy| {
+                (0..self.width)
+                    .map(|dx| {
+                        let pos = self.min + Point2::xy(dx, dy);
+                        match knots.iter().position(|k| *k == pos) {
+                            Some(idx) => knot_label(idx, knots.len()),
+                            None if pos == Point2::origin() => 's',
+                            None if visited.contains(&pos) => '#',
+                            None => '.',
+                        }
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
     }
-    tail_moves.len().to_string()
 }
 
-fn part2(input: &InputType) -> String {
-    let mut head = Point::new(0, 0);
-    let mut body = vec![Point::new(0,0); 8];
-    let mut tail = Point::new(0, 0);
-    let mut tail_moves = HashSet::new();
-    tail_moves.insert(tail);
-    for dir in input.iter() {
-        let dist = dir.val();
-        for _ in 0..dist {
-            head.make_move(dir);
-
-            // connect head with body
-            if let Some(tail_dir) = Point::get_tail_move(&head, &body.first().unwrap()) {
-                body.first_mut().unwrap().make_move(&tail_dir);
-            }
-
-            for idx in 0..(body.len()-1) {
-                let body_head = body.get(idx).unwrap();
-                let body_tail = body.get(idx + 1).unwrap();
-                if let Some(tail_dir) = Point::get_tail_move(body_head, body_tail) {
-                    body.get_mut(idx + 1).unwrap().make_move(&tail_dir);
-                }
-            }
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Frame {
+    pub step: usize,
+    pub motion: Option<Motion>,
+    pub picture: String,
+}
 
-            // connect body with tail
-            if let Some(tail_dir) = Point::get_tail_move(&body.last().unwrap(), &tail) {
-                tail.make_move(&tail_dir);
-                tail_moves.insert(tail);
-            }
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.motion {
+            None => writeln!(f, "== Initial State ==")?,
+            Some(motion) => writeln!(f, "== {} (step {}) ==", motion, self.step)?,
         }
+        write!(f, "\n{}\n", self.picture)
+    }
+}
 
+// One frame per single step, marking the positions visited by the `tracked` knot
+pub fn animate(rope: &mut Rope, motions: &[Motion], tracked: usize, mut viewport: Viewport) -> Vec<Frame> {
+    let mut visited = HashSet::from([rope.knots[tracked]]);
+    viewport.follow(rope.knots());
+    let mut frames = vec![Frame { step: 0, motion: None, picture: viewport.render(rope.knots(), &visited) }];
+    for motion in motions {
+        for _ in 0..motion.steps {
+            rope.step(motion.direction);
+            visited.insert(rope.knots[tracked]);
+            viewport.follow(rope.knots());
+            frames.push(Frame {
+                step: frames.len(),
+                motion: Some(*motion),
+                picture: viewport.render(rope.knots(), &visited),
+            });
+        }
     }
-    tail_moves.len().to_string()
+    frames
+}
+
+// Plain text animation, frames one after another separated by blank lines
+pub fn save_animation(path: &Path, frames: &[Frame]) -> io::Result<()> {
+    fs::write(path, frames.iter().map(|f| f.to_string()).collect::<Vec<_>>().join("\n"))
+}
+
+fn count_tail_positions(input: &InputType, knots: usize) -> usize {
+    Rope::new(knots).visited(input, knots - 1).len()
+}
+
+fn part1(input: &InputType) -> String {
+    count_tail_positions(input, 2).to_string()
+}
+
+fn part2(input: &InputType) -> String {
+    count_tail_positions(input, 10).to_string()
 }
 
-type InputType = Vec<Direction>;
+type InputType = Vec<Motion>;
 fn parse_input(raw_input: &[String]) -> InputType {
     raw_input.iter().map(|x| x.parse().unwrap()).collect()
 }
@@ -1,91 +1,409 @@
+use std::{fmt, io, path::Path, str::FromStr};
+
+use serde_json::json;
+
 use crate::common::Solution;
+use crate::grid::Grid;
+use crate::ocr;
+use crate::points::Point2;
+use crate::render::{self, Image, Rgb};
+
+const CRT_WIDTH: usize = 40;
+const CRT_HEIGHT: usize = 6;
+const LIT_COLOR: Rgb = [255, 176, 0];
+const BEAM_COLOR: Rgb = [255, 0, 0];
 
-enum Instruction {
-    noop,
-    sety(i32),
-    addxy,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    Noop,
+    Addx(i64),
 }
 
-#[derive(Default)]
-struct Cpu {
+impl Instruction {
+    pub fn cycles(&self) -> usize {
+        match self {
+            Instruction::Noop => 1,
+            Instruction::Addx(_) => 2,
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instruction::Noop => write!(f, "noop"),
+            Instruction::Addx(v) => write!(f, "addx {}", v),
+        }
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(' ') {
+            None if s == "noop" => Ok(Instruction::Noop),
+            Some(("addx", val)) => val.parse().map(Instruction::Addx).map_err(|_| ()),
+            _ => Err(()),
+        }
+    }
+}
+
+// Registers as seen during a cycle, before the instruction in flight takes effect
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CycleState {
+    pub cycle: usize,
+    pub x: i64,
+    pub pc: usize,
+}
+
+impl CycleState {
+    pub fn beam(&self) -> usize {
+        (self.cycle - 1) % (CRT_WIDTH * CRT_HEIGHT)
+    }
+
+    pub fn is_lit(&self) -> bool {
+        self.x.abs_diff((self.beam() % CRT_WIDTH) as i64) <= 1
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tick {
+    pub state: CycleState,
+    pub retired: Option<Instruction>,
+}
+
+pub trait CpuObserver {
+    fn on_cycle(&mut self, _state: &CycleState) {}
+    fn on_instruction(&mut self, _instruction: &Instruction, _cpu: &Cpu) {}
+}
+
+pub struct Cpu<'a> {
+    program: &'a [Instruction],
     x: i64,
-    y: i64,
-    cycle: usize
+    cycle: usize,
+    pc: usize,
+    cycles_left: usize,
 }
 
-impl Cpu {
-    fn new() -> Self {
-        Cpu { x: 1, y: 0, cycle: 0}
+impl<'a> Cpu<'a> {
+    pub fn new(program: &'a [Instruction]) -> Self {
+        Cpu { program, x: 1, cycle: 0, pc: 0, cycles_left: 0 }
+    }
+
+    pub fn x(&self) -> i64 {
+        self.x
+    }
+
+    pub fn cycle(&self) -> usize {
+        self.cycle
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc
     }
 
-    fn bump_cycle(&mut self) {
+    pub fn is_halted(&self) -> bool {
+        self.pc >= self.program.len()
+    }
+
+    pub fn current_instruction(&self) -> Option<Instruction> {
+        self.program.get(self.pc).copied()
+    }
+
+    // True between instructions, when the next tick starts a new one
+    pub fn at_boundary(&self) -> bool {
+        self.cycles_left == 0
+    }
+
+    pub fn tick(&mut self) -> Option<Tick> {
+        let instruction = *self.program.get(self.pc)?;
+        if self.cycles_left == 0 {
+            self.cycles_left = instruction.cycles();
+        }
+
         self.cycle += 1;
+        let state = CycleState { cycle: self.cycle, x: self.x, pc: self.pc };
+
+        self.cycles_left -= 1;
+        if self.cycles_left > 0 {
+            return Some(Tick { state, retired: None });
+        }
+
+        match instruction {
+            Instruction::Noop => (),
+            Instruction::Addx(v) => self.x += v,
+        }
+        self.pc += 1;
+        Some(Tick { state, retired: Some(instruction) })
     }
 
-    fn perform(&mut self, task: &Instruction) {
-        match task {
-            Instruction::noop => (),
-            Instruction::sety(v) => self.y = *v as i64,
-            Instruction::addxy => self.x += self.y
+    pub fn run(&mut self, observer: &mut impl CpuObserver) {
+        while let Some(tick) = self.tick() {
+            observer.on_cycle(&tick.state);
+            if let Some(instruction) = tick.retired {
+                observer.on_instruction(&instruction, self);
+            }
         }
     }
 
-    fn get_signal_strength(&self) -> i64 {
-        self.x * (self.cycle as i64)
+    pub fn trace(&mut self) -> Vec<CycleState> {
+        let mut trace = Trace(Vec::new());
+        self.run(&mut trace);
+        trace.0
     }
 }
 
-fn part1(input: &InputType) -> String {
-    let mut cpu = Cpu::new();
-    let mut next_check = 19;
-    let mut signal_sum = 0;
-    for i in 0..input.len() {
-        cpu.bump_cycle();
-
-        if i == next_check {
-            let sum = cpu.get_signal_strength();
-            signal_sum += sum;
-            next_check += 40;
+pub struct Trace(pub Vec<CycleState>);
+
+impl CpuObserver for Trace {
+    fn on_cycle(&mut self, state: &CycleState) {
+        self.0.push(*state);
+    }
+}
+
+struct SignalProbe {
+    first: usize,
+    every: usize,
+    sum: i64,
+}
+
+impl CpuObserver for SignalProbe {
+    fn on_cycle(&mut self, state: &CycleState) {
+        if state.cycle >= self.first && (state.cycle - self.first).is_multiple_of(self.every) {
+            self.sum += state.x * state.cycle as i64;
         }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CrtFrame {
+    pub cycle: usize,
+    pub beam: usize,
+    pub screen: Grid<bool>,
+}
 
-        let task = input.get(i).unwrap();
-        cpu.perform(task);
+impl CrtFrame {
+    // Same as the final picture, with the pixel under the beam marked in red
+    pub fn to_image(&self, scale: usize) -> Image {
+        let mut image = crt_image(&self.screen, scale);
+        image.fill_cell(self.beam % CRT_WIDTH, self.beam / CRT_WIDTH, scale, BEAM_COLOR);
+        image
     }
-    signal_sum.to_string()
 }
 
-fn part2(input: &InputType) -> String {
-    let mut cpu = Cpu::new();
-    let mut crt = ['.'; 40*6];
+pub struct Crt {
+    screen: Grid<bool>,
+    frames: Option<Vec<CrtFrame>>,
+}
+
+impl Crt {
+    pub fn new() -> Self {
+        Crt { screen: Grid::filled(CRT_HEIGHT, CRT_WIDTH, false), frames: None }
+    }
+
+    // Also keeps a snapshot of the screen after every cycle
+    pub fn recording() -> Self {
+        Crt { frames: Some(Vec::new()), ..Self::new() }
+    }
+
+    pub fn screen(&self) -> &Grid<bool> {
+        &self.screen
+    }
 
-    for i in 0..(input.len() as i64) {
-        cpu.bump_cycle();
-        if cpu.x.abs_diff(i % 40) <= 1 {
-            *crt.get_mut(i as usize).unwrap() = '#';
+    pub fn frames(&self) -> &[CrtFrame] {
+        self.frames.as_deref().unwrap_or_default()
+    }
+
+    pub fn to_terminal(&self) -> String {
+        render::render_blocks(&self.screen)
+    }
+
+    pub fn to_image(&self, scale: usize) -> Image {
+        crt_image(&self.screen, scale)
+    }
+
+    // Writes frame_0001.<ext> and onwards into dir, returning how many were written
+    pub fn save_frames(&self, dir: &Path, scale: usize, ext: &str) -> io::Result<usize> {
+        for frame in self.frames() {
+            let path = dir.join(format!("frame_{:04}.{}", frame.cycle, ext));
+            frame.to_image(scale).save(&path)?;
+        }
+        Ok(self.frames().len())
+    }
+}
+
+impl Default for Crt {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CpuObserver for Crt {
+    fn on_cycle(&mut self, state: &CycleState) {
+        let beam = state.beam();
+        let pos = Point2::xy((beam % CRT_WIDTH) as i32, (beam / CRT_WIDTH) as i32);
+        if state.is_lit() {
+            self.screen[pos] = true;
+        }
+        if let Some(frames) = self.frames.as_mut() {
+            frames.push(CrtFrame { cycle: state.cycle, beam, screen: self.screen.clone() });
+        }
+    }
+}
+
+fn crt_image(screen: &Grid<bool>, scale: usize) -> Image {
+    Image::from_grid(screen, scale, |lit| if *lit { LIT_COLOR } else { render::BLACK })
+}
+
+pub fn draw(program: &[Instruction], record_frames: bool) -> Crt {
+    let mut crt = if record_frames { Crt::recording() } else { Crt::new() };
+    Cpu::new(program).run(&mut crt);
+    crt
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Breakpoint {
+    // Stop before the given 1-based cycle runs
+    Cycle(usize),
+    // Stop before the instruction at the given program index starts
+    Instruction(usize),
+}
+
+pub struct Debugger<'a> {
+    cpu: Cpu<'a>,
+    breakpoints: Vec<Breakpoint>,
+    // Breakpoints already reported at the cycle we are stopped at
+    reported: (usize, Vec<Breakpoint>),
+    trace: Vec<CycleState>,
+}
+
+impl<'a> Debugger<'a> {
+    pub fn new(program: &'a [Instruction]) -> Self {
+        Debugger { cpu: Cpu::new(program), breakpoints: Vec::new(), reported: (0, Vec::new()), trace: Vec::new() }
+    }
+
+    pub fn cpu(&self) -> &Cpu<'a> {
+        &self.cpu
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.push(breakpoint);
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    fn hit_breakpoint(&self) -> Option<Breakpoint> {
+        let reported = if self.reported.0 == self.cpu.cycle { self.reported.1.as_slice() } else { &[] };
+        self.breakpoints.iter().copied().filter(|bp| !reported.contains(bp)).find(|bp| match bp {
+            Breakpoint::Cycle(cycle) => self.cpu.cycle + 1 == *cycle,
+            Breakpoint::Instruction(pc) => self.cpu.at_boundary() && self.cpu.pc == *pc,
+        })
+    }
+
+    // Runs a single cycle
+    pub fn step(&mut self) -> Option<Tick> {
+        let tick = self.cpu.tick()?;
+        self.trace.push(tick.state);
+        Some(tick)
+    }
+
+    // Runs cycles until the instruction in flight retires
+    pub fn step_instruction(&mut self) -> Option<Instruction> {
+        loop {
+            if let Some(instruction) = self.step()?.retired {
+                return Some(instruction);
+            }
+        }
+    }
+
+    // Runs until the next breakpoint, or returns None once the program ends.
+    // Breakpoints at the current position count too, except those already reported there.
+    pub fn resume(&mut self) -> Option<Breakpoint> {
+        loop {
+            if let Some(breakpoint) = self.hit_breakpoint() {
+                if self.reported.0 != self.cpu.cycle {
+                    self.reported = (self.cpu.cycle, Vec::new());
+                }
+                self.reported.1.push(breakpoint);
+                return Some(breakpoint);
+            }
+            self.step()?;
         }
+    }
+
+    pub fn dump(&self) -> String {
+        let x = self.cpu.x;
+        let sprite: String = (0..CRT_WIDTH as i64)
+            .map(|col| if x.abs_diff(col) <= 1 { '#' } else { '.' })
+            .collect();
+        let next = match self.cpu.current_instruction() {
+            Some(instruction) if self.cpu.at_boundary() => format!("next {}", instruction),
+            Some(instruction) => format!("in flight {}", instruction),
+            None => "halted".to_string(),
+        };
+        format!(
+            "cycle {} pc {} ({})\nX = {}, sprite at {}..={}\n{}",
+            self.cpu.cycle, self.cpu.pc, next, x, x - 1, x + 1, sprite
+        )
+    }
+
+    pub fn trace(&self) -> &[CycleState] {
+        &self.trace
+    }
+
+    pub fn trace_text(&self) -> String {
+        self.trace
+            .iter()
+            .map(|s| {
+                format!(
+                    "cycle {:>3} pc {:>3} X {:>3} beam {:>2} {}",
+                    s.cycle,
+                    s.pc,
+                    s.x,
+                    s.beam() % CRT_WIDTH,
+                    if s.is_lit() { '#' } else { '.' }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 
-        let task = input.get(i as usize).unwrap();
-        cpu.perform(task);
+    pub fn trace_json(&self) -> String {
+        let cycles: Vec<_> = self
+            .trace
+            .iter()
+            .map(|s| {
+                json!({
+                    "cycle": s.cycle,
+                    "pc": s.pc,
+                    "instruction": self.cpu.program[s.pc].to_string(),
+                    "x": s.x,
+                    "beam": s.beam(),
+                    "lit": s.is_lit(),
+                })
+            })
+            .collect();
+        serde_json::to_string_pretty(&cycles).unwrap()
     }
-    // Read the result manually
-    // crt.chunks(40).for_each(|line| {
-    //     for l in line {
-    //         print!("{}", l);
-    //     }
-    //     println!("");
-    // });
-    "EKRHEPUZ".to_string()
+}
+
+fn part1(input: &InputType) -> String {
+    let mut probe = SignalProbe { first: 20, every: 40, sum: 0 };
+    Cpu::new(input).run(&mut probe);
+    probe.sum.to_string()
+}
+
+fn part2(input: &InputType) -> String {
+    let crt = draw(input, false);
+    ocr::read_grid(crt.screen()).unwrap_or_else(|e| panic!("{}", e))
 }
 
 type InputType = Vec<Instruction>;
 fn parse_input(raw_input: &[String]) -> InputType {
-    raw_input.iter().flat_map(|x| {
-        if let Some(addx) = x.strip_prefix("addx ") {
-            vec![Instruction::sety(addx.parse().unwrap()), Instruction::addxy]
-        } else {
-            vec![Instruction::noop]
-        }
-    }).collect()
+    raw_input.iter().map(|x| x.parse().unwrap()).collect()
 }
 
 pub fn solve(raw_input: &[String]) -> Solution {
@@ -97,3 +415,40 @@ pub fn solve(raw_input: &[String]) -> Solution {
     let elapsed = now.elapsed();
     (solution, elapsed)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program() -> Vec<Instruction> {
+        ["noop", "addx 3", "addx -5"].iter().map(|x| x.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn breakpoints_fire_before_the_first_step() {
+        let program = program();
+        let mut debugger = Debugger::new(&program);
+        debugger.add_breakpoint(Breakpoint::Cycle(1));
+        debugger.add_breakpoint(Breakpoint::Instruction(0));
+
+        assert_eq!(debugger.resume(), Some(Breakpoint::Cycle(1)));
+        assert_eq!(debugger.resume(), Some(Breakpoint::Instruction(0)));
+        assert_eq!(debugger.cpu().cycle(), 0);
+        assert_eq!(debugger.resume(), None);
+        assert_eq!(debugger.cpu().cycle(), 5);
+    }
+
+    #[test]
+    fn resume_moves_past_the_current_breakpoint() {
+        let program = program();
+        let mut debugger = Debugger::new(&program);
+        debugger.add_breakpoint(Breakpoint::Instruction(1));
+        debugger.add_breakpoint(Breakpoint::Cycle(4));
+
+        assert_eq!(debugger.resume(), Some(Breakpoint::Instruction(1)));
+        assert_eq!(debugger.cpu().cycle(), 1);
+        assert_eq!(debugger.resume(), Some(Breakpoint::Cycle(4)));
+        assert_eq!(debugger.cpu().cycle(), 3);
+        assert_eq!(debugger.resume(), None);
+    }
+}
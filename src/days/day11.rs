@@ -1,64 +1,300 @@
-use std::collections::VecDeque;
+use std::{collections::HashMap, fmt, hash::Hash, iter::Peekable, str::Chars};
 
 use itertools::Itertools;
+#[cfg(feature = "bigint")]
+use num_bigint::BigUint;
+#[cfg(feature = "bigint")]
+use num_traits::{CheckedSub, ToPrimitive};
 
 use crate::common::Solution;
 
+pub trait Worry: Clone + Eq + Hash + fmt::Debug + fmt::Display {
+    fn from_u64(v: u64) -> Self;
+    fn to_u64(&self) -> Option<u64>;
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    fn checked_sub(&self, other: &Self) -> Option<Self>;
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+    fn div_u64(&self, d: u64) -> Self;
+    fn rem_u64(&self, m: u64) -> u64;
+}
+
+impl Worry for u64 {
+    fn from_u64(v: u64) -> Self {
+        v
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        Some(*self)
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        u64::checked_add(*self, *other)
+    }
+
+    fn checked_sub(&self, other: &Self) -> Option<Self> {
+        u64::checked_sub(*self, *other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        u64::checked_mul(*self, *other)
+    }
+
+    fn div_u64(&self, d: u64) -> Self {
+        self / d
+    }
+
+    fn rem_u64(&self, m: u64) -> u64 {
+        self % m
+    }
+}
+
+// Exact worry levels, which only ever fail on going below 0
+#[cfg(feature = "bigint")]
+impl Worry for BigUint {
+    fn from_u64(v: u64) -> Self {
+        BigUint::from(v)
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        ToPrimitive::to_u64(self)
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(self + other)
+    }
+
+    fn checked_sub(&self, other: &Self) -> Option<Self> {
+        CheckedSub::checked_sub(self, other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        Some(self * other)
+    }
+
+    fn div_u64(&self, d: u64) -> Self {
+        self / d
+    }
+
+    fn rem_u64(&self, m: u64) -> u64 {
+        ToPrimitive::to_u64(&(self % m)).unwrap()
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Debug)]
-struct Monkey {
+pub struct Monkey {
     items: Vec<u64>,
     test_num: u64,
     test_passed_idx: usize,
     test_failed_idx: usize,
     inspections: u64,
-    op_component: Comp,
-    op_is_mult: bool,
+    operation: Expr,
 }
 
 #[derive(Clone, PartialEq, Eq, Debug)]
-enum Comp {
+enum Expr {
     Old,
-    Val(u64)
+    Val(u64),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
 }
 
-impl Monkey {
+impl Expr {
+    // None when the result does not fit into W, including going below 0.
+    // Under a modulus every intermediate result is reduced, so nothing outgrows it.
+    fn eval<W: Worry>(&self, old: &W, modulus: Option<u64>) -> Option<W> {
+        let (l, r) = match self {
+            Expr::Old => return Some(old.clone()),
+            Expr::Val(v) => return Some(W::from_u64(*v)),
+            Expr::Add(l, r) | Expr::Sub(l, r) | Expr::Mul(l, r) => (l.eval(old, modulus)?, r.eval(old, modulus)?),
+        };
+        match modulus {
+            Some(m) => {
+                let (a, b, m) = (l.rem_u64(m) as u128, r.rem_u64(m) as u128, m as u128);
+                let reduced = match self {
+                    Expr::Add(..) => (a + b) % m,
+                    Expr::Sub(..) => (a + m - b) % m,
+                    _ => a * b % m,
+                };
+                Some(W::from_u64(reduced as u64))
+            }
+            None => match self {
+                Expr::Add(..) => l.checked_add(&r),
+                Expr::Sub(..) => l.checked_sub(&r),
+                _ => l.checked_mul(&r),
+            },
+        }
+    }
+
+    fn parse(s: &str) -> Result<Expr, String> {
+        let mut parser = ExprParser { chars: s.chars().peekable(), pos: 0 };
+        let expr = parser.expr()?;
+        parser.skip_spaces();
+        match parser.chars.peek() {
+            None => Ok(expr),
+            Some(c) => Err(format!("unexpected '{}' at {}", c, parser.pos)),
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Old => write!(f, "old"),
+            Expr::Val(v) => write!(f, "{}", v),
+            Expr::Add(l, r) => write!(f, "{} + {}", l, r),
+            // Right-hand sums need brackets to keep their meaning
+            Expr::Sub(l, r) => match **r {
+                Expr::Add(..) | Expr::Sub(..) => write!(f, "{} - ({})", l, r),
+                _ => write!(f, "{} - {}", l, r),
+            },
+            Expr::Mul(l, r) => {
+                let wrap = |e: &Expr| match e {
+                    Expr::Add(..) | Expr::Sub(..) => format!("({})", e),
+                    _ => e.to_string(),
+                };
+                write!(f, "{} * {}", wrap(l), wrap(r))
+            }
+        }
+    }
+}
+
+// expr := term (('+' | '-') term)*, term := factor ('*' factor)*, factor := num | old | '(' expr ')'
+struct ExprParser<'a> {
+    chars: Peekable<Chars<'a>>,
+    pos: usize,
+}
+
+impl ExprParser<'_> {
+    fn bump(&mut self) -> Option<char> {
+        self.pos += 1;
+        self.chars.next()
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.chars.peek().is_some_and(|c| c.is_whitespace()) {
+            self.bump();
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        let mut left = self.term()?;
+        loop {
+            self.skip_spaces();
+            match self.chars.peek() {
+                Some('+') => {
+                    self.bump();
+                    left = Expr::Add(Box::new(left), Box::new(self.term()?));
+                }
+                Some('-') => {
+                    self.bump();
+                    left = Expr::Sub(Box::new(left), Box::new(self.term()?));
+                }
+                _ => return Ok(left),
+            }
+        }
+    }
 
-    fn operation(&self, item: u64) -> u64 {
-        match self.op_component {
-            Comp::Old => if self.op_is_mult { item * item } else { item + item },
-            Comp::Val(v) => if self.op_is_mult { item * v } else { item + v },
+    fn term(&mut self) -> Result<Expr, String> {
+        let mut left = self.factor()?;
+        loop {
+            self.skip_spaces();
+            if self.chars.peek() != Some(&'*') {
+                return Ok(left);
+            }
+            self.bump();
+            left = Expr::Mul(Box::new(left), Box::new(self.factor()?));
         }
     }
 
-    fn from(s: &[String]) -> Result<Self, ()> {
-        if s.len() != 7 {
-            println!("Err while parsing! Got {} lines instead of 7!", s.len());
-            return Err(());
+    fn factor(&mut self) -> Result<Expr, String> {
+        self.skip_spaces();
+        let start = self.pos;
+        match self.chars.peek().copied() {
+            Some('(') => {
+                self.bump();
+                let inner = self.expr()?;
+                self.skip_spaces();
+                match self.bump() {
+                    Some(')') => Ok(inner),
+                    _ => Err(format!("unclosed '(' opened at {}", start)),
+                }
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let mut num = String::new();
+                while let Some(d) = self.chars.peek().copied().filter(char::is_ascii_digit) {
+                    num.push(d);
+                    self.bump();
+                }
+                num.parse().map(Expr::Val).map_err(|_| format!("number too large at {}", start))
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                let mut word = String::new();
+                while let Some(l) = self.chars.peek().copied().filter(char::is_ascii_alphanumeric) {
+                    word.push(l);
+                    self.bump();
+                }
+                match word.as_str() {
+                    "old" => Ok(Expr::Old),
+                    _ => Err(format!("unknown name '{}' at {}", word, start)),
+                }
+            }
+            Some(c) => Err(format!("unexpected '{}' at {}", c, start)),
+            None => Err(format!("missing operand at {}", start)),
+        }
+    }
+}
+
+impl Monkey {
+
+    fn operation<W: Worry>(&self, item: &W, modulus: Option<u64>) -> W {
+        self.operation.eval(item, modulus)
+            .unwrap_or_else(|| panic!("new = {} overflows for old = {}", self.operation, item))
+    }
+
+    // Six lines, or seven with the blank one separating monkeys
+    fn from(s: &[String]) -> Result<Self, String> {
+        if s.len() != 6 && s.len() != 7 {
+            return Err(format!("got {} lines instead of 7", s.len()));
         }
 
-        let items = s.get(1).unwrap().trim_start().strip_prefix("Starting items: ").unwrap().split(", ").into_iter().map(|s| s.parse().unwrap()).collect();
-        let test_num = s.get(3).unwrap().trim_start().strip_prefix("Test: divisible by ").unwrap().parse().unwrap();
-        let test_passed_idx = s.get(4).unwrap().trim_start().strip_prefix("If true: throw to monkey ").unwrap().parse().unwrap();
-        let test_failed_idx = s.get(5).unwrap().trim_start().strip_prefix("If false: throw to monkey ").unwrap().parse().unwrap();
-        let op_str = s.get(2).unwrap().trim_start().strip_prefix("Operation: new = old ").unwrap();
-        let op_is_mult = op_str.contains("*");
-        let delim = if op_is_mult {"* "} else {"+ "};
-        let right = op_str.strip_prefix(delim).unwrap();
-        let op_component = match right {
-            "old" => Comp::Old,
-            v => Comp::Val(v.parse().unwrap())
+        let field = |idx: usize, prefix: &str| {
+            let line = s[idx].trim_start();
+            line.strip_prefix(prefix).ok_or_else(|| format!("expected '{}', got '{}'", prefix.trim_end(), line))
         };
-        Ok(Self { items, test_num, test_passed_idx, test_failed_idx, inspections: 0, op_component, op_is_mult})
+        let number = |idx: usize, prefix: &str| {
+            let val = field(idx, prefix)?;
+            val.parse().map_err(|_| format!("invalid number '{}' in line {}", val, idx + 1))
+        };
+
+        let items = field(1, "Starting items: ")?
+            .split(", ")
+            .map(|item| item.parse().map_err(|_| format!("invalid item '{}'", item)))
+            .collect::<Result<_, _>>()?;
+        let test_num = number(3, "Test: divisible by ")?;
+        let test_passed_idx = number(4, "If true: throw to monkey ")? as usize;
+        let test_failed_idx = number(5, "If false: throw to monkey ")? as usize;
+        let op_str = field(2, "Operation: new = ")?;
+        let operation = Expr::parse(op_str).map_err(|err| format!("invalid operation '{}': {}", op_str, err))?;
+        Ok(Self { items, test_num, test_passed_idx, test_failed_idx, inspections: 0, operation })
     }
 
-    fn inspect(&mut self, item: u64, drop_worry: bool) -> (usize, u64) {
+    fn inspect<W: Worry>(&mut self, item: &W, relief: &Relief, modulus: Option<u64>) -> (usize, W) {
         self.inspections += 1;
+        self.throw(item, relief, modulus)
+    }
+
+    fn throw<W: Worry>(&self, item: &W, relief: &Relief, modulus: Option<u64>) -> (usize, W) {
         // inspect
-        let item_op = self.operation(item);
+        let item_op = self.operation(item, modulus);
         // get bored
-        let new_item = if drop_worry { item_op / 3 } else { item_op};
+        let new_item = relief.apply(item_op);
+        let new_item = match modulus {
+            Some(m) => W::from_u64(new_item.rem_u64(m)),
+            None => new_item,
+        };
         // test
-        if new_item % self.test_num == 0 {
+        if new_item.rem_u64(self.test_num) == 0 {
             (self.test_passed_idx, new_item)
         } else {
             (self.test_failed_idx, new_item)
@@ -67,76 +303,228 @@ impl Monkey {
 
 }
 
-fn part1(input: &InputType) -> String {
-    let mut monkes = input.clone();
-    let mut current_items: VecDeque<(usize, u64)> = input.iter()
-    .enumerate()
-    .flat_map(|(idx, m)| m.items.iter().map(move |item| (idx, *item)))
-    .collect();
-    let mut future_items: VecDeque<(usize, u64)> = VecDeque::new();
-    for _ in 0..20 {
-        while let Some((idx, item)) = current_items.pop_front() {
-            let monke = monkes.get_mut(idx).unwrap();
-
-            let (next_idx, next_item) = monke.inspect(item, true);
-
-            if next_idx > idx {
-                current_items.push_back((next_idx, next_item));
-            } else {
-                future_items.push_back((next_idx, next_item));
+#[derive(Clone, Copy, Debug)]
+pub enum Relief {
+    None,
+    DivideBy(u64),
+    Custom(fn(u64) -> u64),
+}
+
+impl Relief {
+    fn apply<W: Worry>(&self, worry: W) -> W {
+        match self {
+            Relief::None => worry,
+            Relief::DivideBy(d) => worry.div_u64(*d),
+            Relief::Custom(f) => {
+                let small = worry.to_u64().unwrap_or_else(|| panic!("{} is too large for a custom relief", worry));
+                W::from_u64(f(small))
             }
         }
-        current_items = future_items.drain(..).collect();
     }
+}
 
-    monkes.iter()
-    .map(|m| m.inspections)
-    .sorted()
-    .rev()
-    .take(2)
-    .product::<u64>()
-    .to_string()
+// Keeps worry levels bounded; only sound when any relief commutes with the modulus
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Reduction {
+    None,
+    ProductOfDivisors,
+    LcmOfDivisors,
+    Modulo(u64),
 }
 
-fn part2(input: &InputType) -> String {
-    let mut monkes = input.clone();
-    let mut current_items: VecDeque<(usize, u64)> = input.iter().enumerate().flat_map(|(idx, m)| m.items.iter().map(move |item| (idx, *item))).collect();
-    let mut future_items: VecDeque<(usize, u64)> = VecDeque::new();
-    let max_item_val: u64 = input.iter().map(|monke| monke.test_num).product();
-    for _ in 0..10000 {
-        while let Some((idx, item)) = current_items.pop_front() {
-            let monke = monkes.get_mut(idx).unwrap();
-
-            let (next_idx, next_item) = monke.inspect(item, false);
-            let next_item = next_item % max_item_val;
-
-            if next_idx > idx {
-                current_items.push_back((next_idx, next_item));
-            } else {
-                future_items.push_back((next_idx, next_item));
+impl Reduction {
+    fn modulus(&self, monkes: &[Monkey]) -> Option<u64> {
+        let divisors = monkes.iter().map(|m| m.test_num);
+        match self {
+            Reduction::None => None,
+            Reduction::ProductOfDivisors => Some(divisors.product()),
+            Reduction::LcmOfDivisors => Some(divisors.fold(1, lcm)),
+            Reduction::Modulo(m) => Some(*m),
+        }
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+fn lcm(a: u64, b: u64) -> u64 {
+    a / gcd(a, b) * b
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RoundSnapshot<W = u64> {
+    pub round: usize,
+    pub items: Vec<Vec<W>>,
+    pub inspections: Vec<u64>,
+}
+
+// Monkeys only provide the rules and inspection counters, the items in flight live in `items`
+#[derive(Clone, Debug)]
+pub struct Simulation<W = u64> {
+    monkes: Vec<Monkey>,
+    items: Vec<Vec<W>>,
+    relief: Relief,
+    modulus: Option<u64>,
+    round: usize,
+}
+
+#[cfg(feature = "bigint")]
+pub type ExactSimulation = Simulation<BigUint>;
+
+impl<W: Worry> Simulation<W> {
+    pub fn new(monkes: &[Monkey], relief: Relief, reduction: Reduction) -> Self {
+        let modulus = reduction.modulus(monkes);
+        let items = monkes.iter()
+            .map(|monke| monke.items.iter().map(|item| W::from_u64(modulus.map_or(*item, |m| item % m))).collect())
+            .collect();
+        Self { monkes: monkes.to_vec(), items, relief, modulus, round: 0 }
+    }
+
+    pub fn round(&self) -> usize {
+        self.round
+    }
+
+    pub fn modulus(&self) -> Option<u64> {
+        self.modulus
+    }
+
+    pub fn step_round(&mut self) {
+        for idx in 0..self.monkes.len() {
+            let items = std::mem::take(&mut self.items[idx]);
+            for item in items.iter() {
+                let (next_idx, next_item) = self.monkes[idx].inspect(item, &self.relief, self.modulus);
+                self.items[next_idx].push(next_item);
+            }
+        }
+        self.round += 1;
+    }
+
+    pub fn run(&mut self, rounds: usize) -> &mut Self {
+        for _ in 0..rounds {
+            self.step_round();
+        }
+        self
+    }
+
+    // Snapshot of the starting state followed by one after every round
+    pub fn run_with_snapshots(&mut self, rounds: usize) -> Vec<RoundSnapshot<W>> {
+        let mut snapshots = vec![self.snapshot()];
+        for _ in 0..rounds {
+            self.step_round();
+            snapshots.push(self.snapshot());
+        }
+        snapshots
+    }
+
+    pub fn snapshot(&self) -> RoundSnapshot<W> {
+        RoundSnapshot {
+            round: self.round,
+            items: self.items.clone(),
+            inspections: self.inspections(),
+        }
+    }
+
+    pub fn inspections(&self) -> Vec<u64> {
+        self.monkes.iter().map(|m| m.inspections).collect()
+    }
+
+    pub fn monkey_business(&self) -> u128 {
+        monkey_business(&self.inspections())
+    }
+
+    // Inspections caused by a single item over one round, following it while it
+    // gets thrown forward to monkeys that have yet to take their turn
+    fn item_round(&self, mut monke: usize, mut worry: W, counts: &mut [u64]) -> (usize, W) {
+        loop {
+            counts[monke] += 1;
+            let (next, next_worry) = self.monkes[monke].throw(&worry, &self.relief, self.modulus);
+            let same_round = next > monke;
+            monke = next;
+            worry = next_worry;
+            if !same_round {
+                return (monke, worry);
+            }
+        }
+    }
+
+    // Items never interact, so each one is followed on its own until its
+    // (monkey, worry) state at the start of a round repeats, then the cycle is
+    // extrapolated. Without a reduction the states may never repeat.
+    fn item_inspections(&self, monke: usize, worry: W, rounds: u64) -> Vec<u64> {
+        let mut seen: HashMap<(usize, W), usize> = HashMap::new();
+        let mut prefix = vec![vec![0; self.monkes.len()]];
+        let mut state = (monke, worry);
+
+        for round in 0..rounds as usize {
+            if let Some(&cycle_start) = seen.get(&state) {
+                let cycle_len = (round - cycle_start) as u64;
+                let remaining = rounds - round as u64;
+                let tail = cycle_start + (remaining % cycle_len) as usize;
+                return (0..self.monkes.len())
+                    .map(|i| {
+                        let per_cycle = prefix[round][i] - prefix[cycle_start][i];
+                        let partial = prefix[tail][i] - prefix[cycle_start][i];
+                        prefix[round][i] + per_cycle * (remaining / cycle_len) + partial
+                    })
+                    .collect();
             }
+            seen.insert(state.clone(), round);
+
+            let mut counts = prefix[round].clone();
+            state = self.item_round(state.0, state.1, &mut counts);
+            prefix.push(counts);
         }
-        current_items = future_items.drain(..).collect();
+        prefix.pop().unwrap()
     }
 
-    monkes.iter()
-    .map(|m| m.inspections)
+    // Total inspections per monkey after `rounds` more rounds, without running them all
+    pub fn extrapolate_inspections(&self, rounds: u64) -> Vec<u64> {
+        let mut totals = self.inspections();
+        for (idx, items) in self.items.iter().enumerate() {
+            for item in items.iter() {
+                let counts = self.item_inspections(idx, item.clone(), rounds);
+                totals.iter_mut().zip(counts).for_each(|(total, c)| *total += c);
+            }
+        }
+        totals
+    }
+
+    pub fn monkey_business_after(&self, rounds: u64) -> u128 {
+        monkey_business(&self.extrapolate_inspections(rounds))
+    }
+}
+
+// Wide enough for the two busiest monkeys after billions of rounds
+fn monkey_business(inspections: &[u64]) -> u128 {
+    inspections.iter()
     .sorted()
     .rev()
     .take(2)
-    .product::<u64>()
+    .map(|count| *count as u128)
+    .product()
+}
+
+fn part1(input: &InputType) -> String {
+    Simulation::<u64>::new(input, Relief::DivideBy(3), Reduction::None)
+    .run(20)
+    .monkey_business()
+    .to_string()
+}
+
+fn part2(input: &InputType) -> String {
+    Simulation::<u64>::new(input, Relief::None, Reduction::LcmOfDivisors)
+    .monkey_business_after(10000)
     .to_string()
 }
 
 type InputType = Vec<Monkey>;
-fn parse_input(raw_input: &[String]) -> InputType {
-    let mut result = Vec::new();
-    for i in (0..raw_input.len()).step_by(7) {
-        if let Ok(monke) = Monkey::from(&raw_input[i..i+7]) {
-            result.push(monke);
-        }
-    }
-    result
+pub fn parse_input(raw_input: &[String]) -> InputType {
+    raw_input
+        .chunks(7)
+        .enumerate()
+        .map(|(i, lines)| Monkey::from(lines).unwrap_or_else(|e| panic!("monkey {}: {}", i, e)))
+        .collect()
 }
 
 pub fn solve(raw_input: &[String]) -> Solution {
@@ -148,3 +536,142 @@ pub fn solve(raw_input: &[String]) -> Solution {
     let elapsed = now.elapsed();
     (solution, elapsed)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monkes(text: &str) -> Vec<Monkey> {
+        let lines: Vec<String> = text.lines().map(|l| l.trim().to_string()).chain(std::iter::once(String::new())).collect();
+        parse_input(&lines)
+    }
+
+    const SUBTRACTING: &str = "Monkey 0:
+        Starting items: 16, 31, 4
+        Operation: new = old * 2 - 3
+        Test: divisible by 3
+        If true: throw to monkey 1
+        If false: throw to monkey 1
+
+        Monkey 1:
+        Starting items: 2, 46
+        Operation: new = old + 4
+        Test: divisible by 5
+        If true: throw to monkey 0
+        If false: throw to monkey 0";
+
+    #[test]
+    fn subtraction_wraps_around_the_modulus() {
+        let input = monkes(SUBTRACTING);
+        let mut exact = Simulation::<u64>::new(&input, Relief::None, Reduction::None);
+        let mut reduced = Simulation::<u64>::new(&input, Relief::None, Reduction::LcmOfDivisors);
+        for _ in 0..10 {
+            exact.step_round();
+            reduced.step_round();
+            assert_eq!(exact.inspections(), reduced.inspections());
+            let exact_items: Vec<Vec<u64>> = exact.items.iter().map(|items| items.iter().map(|i| i % 15).collect()).collect();
+            assert_eq!(exact_items, reduced.items);
+        }
+    }
+
+    const EXAMPLE: &str = "Monkey 0:
+        Starting items: 79, 98
+        Operation: new = old * 19
+        Test: divisible by 23
+        If true: throw to monkey 2
+        If false: throw to monkey 3
+
+        Monkey 1:
+        Starting items: 54, 65, 75, 74
+        Operation: new = old + 6
+        Test: divisible by 19
+        If true: throw to monkey 2
+        If false: throw to monkey 0
+
+        Monkey 2:
+        Starting items: 79, 60, 97
+        Operation: new = old * old
+        Test: divisible by 13
+        If true: throw to monkey 1
+        If false: throw to monkey 3
+
+        Monkey 3:
+        Starting items: 74
+        Operation: new = old + 3
+        Test: divisible by 17
+        If true: throw to monkey 0
+        If false: throw to monkey 1";
+
+    #[test]
+    fn extrapolation_matches_running_the_rounds() {
+        let sim = Simulation::<u64>::new(&monkes(EXAMPLE), Relief::None, Reduction::LcmOfDivisors);
+        for rounds in [1, 7, 100, 1000, 5000] {
+            assert_eq!(sim.extrapolate_inspections(rounds), sim.clone().run(rounds as usize).inspections(), "{} rounds", rounds);
+        }
+        assert_eq!(sim.monkey_business_after(10000), 2713310158);
+    }
+
+    #[test]
+    fn monkey_business_does_not_overflow_after_a_billion_rounds() {
+        let sim = Simulation::<u64>::new(&monkes(EXAMPLE), Relief::None, Reduction::LcmOfDivisors);
+        let inspections = sim.extrapolate_inspections(1_000_000_000);
+        let top: Vec<u128> = inspections.iter().sorted().rev().take(2).map(|c| *c as u128).collect();
+        assert!(top[0] * top[1] > u64::MAX as u128);
+        assert_eq!(sim.monkey_business_after(1_000_000_000), top[0] * top[1]);
+    }
+
+    #[test]
+    fn every_operator_is_reduced_under_a_modulus() {
+        let m = 9699690;
+        let cube = Expr::parse("old * old * old").unwrap();
+        assert_eq!(cube.eval(&(m - 1), Some(m)), Some(m - 1));
+        let mixed = Expr::parse("(old + old) * old - 5").unwrap();
+        let exact = (2 * (m - 2) as u128 * (m - 2) as u128 - 5) % m as u128;
+        assert_eq!(mixed.eval(&(m - 2), Some(m)), Some(exact as u64));
+    }
+
+    #[test]
+    fn last_monkey_needs_no_trailing_blank_line() {
+        let lines: Vec<String> = SUBTRACTING.lines().map(|l| l.trim().to_string()).collect();
+        assert_eq!(lines.len(), 13);
+        assert_eq!(parse_input(&lines).len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "monkey 0: invalid item 'x'")]
+    fn invalid_item_panics() {
+        monkes(&SUBTRACTING.replace("16, 31", "16, x"));
+    }
+
+    #[test]
+    #[should_panic(expected = "monkey 1: expected 'If true: throw to monkey', got 'If maybe: throw to monkey 0'")]
+    fn invalid_throw_panics() {
+        monkes(&SUBTRACTING.replace("If true: throw to monkey 0", "If maybe: throw to monkey 0"));
+    }
+
+    #[test]
+    #[should_panic(expected = "monkey 1: invalid operation 'old / 2'")]
+    fn invalid_operation_panics_with_the_parse_error() {
+        monkes(&SUBTRACTING.replace("old + 4", "old / 2"));
+    }
+}
+
+#[cfg(all(test, feature = "bigint"))]
+mod exact_tests {
+    use super::*;
+
+    #[test]
+    fn exact_worries_agree_with_the_lcm_reduction() {
+        let input = parse_input(&crate::common::get_day_input(11).unwrap());
+        let mut exact = ExactSimulation::new(&input, Relief::None, Reduction::None);
+        let mut reduced = Simulation::<u64>::new(&input, Relief::None, Reduction::LcmOfDivisors);
+        let m = reduced.modulus().unwrap();
+        for _ in 0..20 {
+            exact.step_round();
+            reduced.step_round();
+            assert_eq!(exact.inspections(), reduced.inspections());
+            let exact_items: Vec<Vec<u64>> = exact.items.iter().map(|items| items.iter().map(|i| i.rem_u64(m)).collect()).collect();
+            assert_eq!(exact_items, reduced.items);
+        }
+    }
+}
@@ -1,33 +1,24 @@
-use std::cmp::Ordering;
+use std::{cmp::Ordering, fmt, iter::Peekable, str::CharIndices, str::FromStr};
 
 use itertools::Itertools;
-use serde_json::Value;
 
 use crate::common::Solution;
 
 #[derive(Debug, Clone)]
-enum PacketValue {
-    Value(i32),
+pub enum PacketValue {
+    Value(i64),
     List(Vec<PacketValue>),
 }
 
 impl Ord for PacketValue {
     fn cmp(&self, other: &Self) -> Ordering {
-        // println!("called cmp!");
-        // println!("comparing {:?} and {:?} = ?", self, other);
-        let ord = match (self, other) {
+        // A lone value compares as a one-element list, borrowed in place rather than wrapped
+        match (self, other) {
             (PacketValue::Value(left), PacketValue::Value(right)) => left.cmp(right),
             (PacketValue::List(left), PacketValue::List(right)) => left.cmp(right),
-            (PacketValue::List(left), right) => left.cmp(&vec![right.clone()]),
-            (left, PacketValue::List(right)) => vec![left.clone()].cmp(right),
-        };
-        // println!(
-        //     "\tcomparing {} and {} = {:?}",
-        //     self.print(),
-        //     other.print(),
-        //     ord
-        // );
-        ord
+            (PacketValue::List(left), right) => left.as_slice().cmp(std::slice::from_ref(right)),
+            (left, PacketValue::List(right)) => std::slice::from_ref(left).cmp(right.as_slice()),
+        }
     }
 }
 
@@ -45,25 +36,148 @@ impl PartialEq for PacketValue {
 
 impl Eq for PacketValue {}
 
-impl PacketValue {
-    fn from(val: &Value) -> PacketValue {
-        match val {
-            Value::Number(v) => PacketValue::Value(v.as_i64().unwrap() as i32),
-            Value::Array(l) => {
-                PacketValue::List(l.iter().map(|vv| PacketValue::from(vv)).collect())
+impl fmt::Display for PacketValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PacketValue::Value(x) => write!(f, "{}", x),
+            PacketValue::List(l) => {
+                write!(f, "[")?;
+                for (i, p) in l.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", p)?;
+                }
+                write!(f, "]")
             }
-            _ => panic!("Value other than Number or Array!"),
         }
     }
+}
 
-    fn print(&self) -> String {
-        match self {
-            PacketValue::Value(x) => format!("{}", x),
-            PacketValue::List(l) => {
-                let ss: Vec<String> = l.iter().map(|p| p.print()).collect();
-                format!("[{}]", ss.join(","))
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PacketErrorKind {
+    UnexpectedChar(char),
+    UnexpectedEnd,
+    NumberTooLarge,
+    TrailingInput,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PacketError {
+    pub pos: usize,
+    pub kind: PacketErrorKind,
+}
+
+impl fmt::Display for PacketError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            PacketErrorKind::UnexpectedChar(c) => write!(f, "unexpected '{}' at position {}", c, self.pos),
+            PacketErrorKind::UnexpectedEnd => write!(f, "packet ends too early at position {}", self.pos),
+            PacketErrorKind::NumberTooLarge => write!(f, "number at position {} is too large", self.pos),
+            PacketErrorKind::TrailingInput => write!(f, "unexpected input after the packet at position {}", self.pos),
+        }
+    }
+}
+
+impl std::error::Error for PacketError {}
+
+// packet := list, list := '[' (value (',' value)*)? ']', value := list | integer,
+// integer := '0' | [1-9][0-9]*
+struct PacketParser<'a> {
+    input: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> PacketParser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self { input, chars: input.char_indices().peekable() }
+    }
+
+    fn pos(&mut self) -> usize {
+        self.chars.peek().map_or(self.input.len(), |(i, _)| *i)
+    }
+
+    fn error<T>(&mut self, kind: PacketErrorKind) -> Result<T, PacketError> {
+        Err(PacketError { pos: self.pos(), kind })
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), PacketError> {
+        self.skip_spaces();
+        match self.chars.peek().copied() {
+            Some((_, c)) if c == expected => {
+                self.chars.next();
+                Ok(())
             }
+            Some((_, c)) => self.error(PacketErrorKind::UnexpectedChar(c)),
+            None => self.error(PacketErrorKind::UnexpectedEnd),
+        }
+    }
+
+    fn packet(&mut self) -> Result<PacketValue, PacketError> {
+        let packet = self.list()?;
+        self.skip_spaces();
+        if self.chars.peek().is_some() {
+            return self.error(PacketErrorKind::TrailingInput);
         }
+        Ok(packet)
+    }
+
+    fn list(&mut self) -> Result<PacketValue, PacketError> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_spaces();
+        if self.chars.next_if(|(_, c)| *c == ']').is_some() {
+            return Ok(PacketValue::List(items));
+        }
+
+        loop {
+            items.push(self.value()?);
+            self.skip_spaces();
+            match self.chars.peek().copied() {
+                Some((_, ',')) => {
+                    self.chars.next();
+                }
+                Some((_, ']')) => {
+                    self.chars.next();
+                    return Ok(PacketValue::List(items));
+                }
+                Some((_, c)) => return self.error(PacketErrorKind::UnexpectedChar(c)),
+                None => return self.error(PacketErrorKind::UnexpectedEnd),
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<PacketValue, PacketError> {
+        self.skip_spaces();
+        match self.chars.peek().copied() {
+            Some((_, '[')) => self.list(),
+            Some((start, c)) if c.is_ascii_digit() => {
+                self.chars.next();
+                // A leading zero is a number on its own, so "01" stops right after it
+                if c != '0' {
+                    while self.chars.next_if(|(_, c)| c.is_ascii_digit()).is_some() {}
+                }
+                let end = self.pos();
+                match self.input[start..end].parse() {
+                    Ok(v) => Ok(PacketValue::Value(v)),
+                    Err(_) => Err(PacketError { pos: start, kind: PacketErrorKind::NumberTooLarge }),
+                }
+            }
+            Some((_, c)) => self.error(PacketErrorKind::UnexpectedChar(c)),
+            None => self.error(PacketErrorKind::UnexpectedEnd),
+        }
+    }
+}
+
+impl FromStr for PacketValue {
+    type Err = PacketError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PacketParser::new(s).packet()
     }
 }
 
@@ -78,22 +192,28 @@ fn part1(input: &InputType) -> String {
         .to_string()
 }
 
-fn part2(input: &InputType) -> String {
+// 1-based positions the dividers would take if inserted into the sorted packets,
+// found by counting smaller packets instead of sorting
+pub fn divider_positions(packets: &[PacketValue], dividers: &[PacketValue]) -> Vec<usize> {
+    let order: Vec<usize> = (0..dividers.len()).sorted_by(|a, b| dividers[*a].cmp(&dividers[*b])).collect();
+    let mut positions = vec![0; dividers.len()];
+    for (rank, idx) in order.into_iter().enumerate() {
+        let smaller = packets.iter().filter(|p| *p < &dividers[idx]).count();
+        positions[idx] = smaller + rank + 1;
+    }
+    positions
+}
+
+pub fn decoder_key(packets: &[PacketValue]) -> usize {
     let dividers: [PacketValue; 2] = [
         PacketValue::List(vec![PacketValue::List(vec![PacketValue::Value(2)])]),
         PacketValue::List(vec![PacketValue::List(vec![PacketValue::Value(6)])]),
     ];
+    divider_positions(packets, &dividers).into_iter().product()
+}
 
-    input
-        .iter()
-        .chain(dividers.iter())
-        .sorted()
-        .into_iter()
-        .enumerate()
-        .filter(|(_, x)| dividers.contains(x))
-        .map(|(i, _)| i + 1)
-        .product::<usize>()
-        .to_string()
+fn part2(input: &InputType) -> String {
+    decoder_key(input).to_string()
 }
 
 type InputType = Vec<PacketValue>;
@@ -101,7 +221,7 @@ fn parse_input(raw_input: &[String]) -> InputType {
     raw_input
         .iter()
         .filter(|x| !x.is_empty())
-        .map(|x| PacketValue::from(&serde_json::from_str(x).unwrap()))
+        .map(|x| x.parse().unwrap_or_else(|err| panic!("Malformed packet {}: {}", x, err)))
         .collect()
 }
 
@@ -114,3 +234,37 @@ pub fn solve(raw_input: &[String]) -> Solution {
     let elapsed = now.elapsed();
     (solution, elapsed)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(s: &str) -> PacketError {
+        s.parse::<PacketValue>().unwrap_err()
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for s in ["[]", "[0]", "[1,[2,[3,[4,[5,6,7]]]],8,9]", "[[[]],10,[0,[]]]", "[9223372036854775807]"] {
+            assert_eq!(s.parse::<PacketValue>().unwrap().to_string(), s);
+        }
+        assert_eq!(" [ 1 , [ ] ] ".parse::<PacketValue>().unwrap().to_string(), "[1,[]]");
+    }
+
+    #[test]
+    fn reports_error_positions() {
+        assert_eq!(error("[1,]"), PacketError { pos: 3, kind: PacketErrorKind::UnexpectedChar(']') });
+        assert_eq!(error("[[1],[2"), PacketError { pos: 7, kind: PacketErrorKind::UnexpectedEnd });
+        assert_eq!(error("[1]]"), PacketError { pos: 3, kind: PacketErrorKind::TrailingInput });
+        assert_eq!(error("[1] x"), PacketError { pos: 4, kind: PacketErrorKind::TrailingInput });
+        assert_eq!(error("[3,9223372036854775808]"), PacketError { pos: 3, kind: PacketErrorKind::NumberTooLarge });
+        assert_eq!(error("1"), PacketError { pos: 0, kind: PacketErrorKind::UnexpectedChar('1') });
+    }
+
+    #[test]
+    fn rejects_leading_zeros_and_signs() {
+        assert_eq!(error("[01]"), PacketError { pos: 2, kind: PacketErrorKind::UnexpectedChar('1') });
+        assert_eq!(error("[-1]"), PacketError { pos: 1, kind: PacketErrorKind::UnexpectedChar('-') });
+        assert_eq!(error("[1,00]"), PacketError { pos: 4, kind: PacketErrorKind::UnexpectedChar('0') });
+    }
+}
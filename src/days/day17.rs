@@ -1,16 +1,24 @@
+use crate::automaton::{Automaton, LifeRule};
 use crate::common::Solution;
+use crate::points::{Point3, Point4};
+
+const BOOT_CYCLES: usize = 6;
 
 fn part1(input: &InputType) -> String {
-    "".to_string()
+    Automaton::<Point3, _>::from_grid(input, LifeRule::conway())
+        .run(BOOT_CYCLES)
+        .to_string()
 }
 
 fn part2(input: &InputType) -> String {
-    "".to_string()
+    Automaton::<Point4, _>::from_grid(input, LifeRule::conway())
+        .run(BOOT_CYCLES)
+        .to_string()
 }
 
 type InputType = Vec<String>;
 fn parse_input(raw_input: &[String]) -> InputType {
-    raw_input.iter().map(|x| x.parse().unwrap()).collect()
+    raw_input.to_vec()
 }
 
 pub fn solve(raw_input: &[String]) -> Solution {
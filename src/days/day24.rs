@@ -1,14 +1,33 @@
+use std::collections::HashSet;
+
+use crate::automaton::{Automaton, LifeRule};
 use crate::common::Solution;
+use crate::points::Hex;
+
+const EXHIBIT_DAYS: usize = 100;
+
+fn flip_tiles(input: &InputType) -> HashSet<Hex> {
+    let mut black = HashSet::new();
+    for tile in input.iter() {
+        if !black.remove(tile) {
+            black.insert(*tile);
+        }
+    }
+    black
+}
 
 fn part1(input: &InputType) -> String {
-    "".to_string()
+    flip_tiles(input).len().to_string()
 }
 
 fn part2(input: &InputType) -> String {
-    "".to_string()
+    let rule = LifeRule::new(&[2], &[1, 2]);
+    Automaton::from_cells(flip_tiles(input), rule)
+        .run(EXHIBIT_DAYS)
+        .to_string()
 }
 
-type InputType = Vec<String>;
+type InputType = Vec<Hex>;
 fn parse_input(raw_input: &[String]) -> InputType {
     raw_input.iter().map(|x| x.parse().unwrap()).collect()
 }
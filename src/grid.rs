@@ -0,0 +1,130 @@
+use std::ops::{Index, IndexMut};
+
+use crate::points::Point2;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Grid<T> {
+    rows: usize,
+    cols: usize,
+    data: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    pub fn new(rows: usize, cols: usize, data: Vec<T>) -> Self {
+        assert_eq!(rows * cols, data.len(), "Grid data does not match {}x{}", cols, rows);
+        Self { rows, cols, data }
+    }
+
+    pub fn filled(rows: usize, cols: usize, val: T) -> Self
+    where
+        T: Clone,
+    {
+        Self::new(rows, cols, vec![val; rows * cols])
+    }
+
+    pub fn from_lines(lines: &[String], mut f: impl FnMut(char) -> T) -> Self {
+        let rows = lines.len();
+        let cols = lines.first().map_or(0, |line| line.chars().count());
+        let data = lines.iter().flat_map(|line| line.chars()).map(&mut f).collect();
+        Self::new(rows, cols, data)
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn contains(&self, pos: &Point2) -> bool {
+        pos.x() >= 0 && pos.y() >= 0 && (pos.x() as usize) < self.cols && (pos.y() as usize) < self.rows
+    }
+
+    fn idx(&self, pos: &Point2) -> Option<usize> {
+        if self.contains(pos) {
+            Some(self.cols * pos.y() as usize + pos.x() as usize)
+        } else {
+            None
+        }
+    }
+
+    fn pos(&self, idx: usize) -> Point2 {
+        Point2::xy((idx % self.cols) as i32, (idx / self.cols) as i32)
+    }
+
+    pub fn get(&self, pos: &Point2) -> Option<&T> {
+        self.idx(pos).map(|idx| &self.data[idx])
+    }
+
+    pub fn get_mut(&mut self, pos: &Point2) -> Option<&mut T> {
+        self.idx(pos).map(|idx| &mut self.data[idx])
+    }
+
+    pub fn row(&self, y: usize) -> &[T] {
+        &self.data[y * self.cols..(y + 1) * self.cols]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Point2, &T)> {
+        self.data.iter().enumerate().map(|(idx, val)| (self.pos(idx), val))
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = Point2> + '_ {
+        (0..self.data.len()).map(|idx| self.pos(idx))
+    }
+
+    pub fn find_all<'a>(&'a self, mut pred: impl FnMut(&T) -> bool + 'a) -> impl Iterator<Item = Point2> + 'a {
+        self.iter().filter(move |(_, val)| pred(val)).map(|(pos, _)| pos)
+    }
+
+    // Orthogonal neighbours that stay inside the grid
+    pub fn neighbours(&self, pos: &Point2) -> impl Iterator<Item = Point2> + '_ {
+        pos.orthogonal_neighbours().filter(move |n| self.contains(n))
+    }
+
+    // Neighbours that `can_step` allows moving into, usable as a search neighbour function
+    pub fn steps<'a>(
+        &'a self,
+        pos: &Point2,
+        can_step: impl Fn(&T, &T) -> bool + 'a,
+    ) -> impl Iterator<Item = Point2> + 'a {
+        let from = &self[*pos];
+        self.neighbours(pos).filter(move |n| can_step(from, &self[*n]))
+    }
+
+    // Same as steps, with `cost` giving the weight of each allowed move
+    pub fn weighted_steps<'a, C>(
+        &'a self,
+        pos: &Point2,
+        cost: impl Fn(&T, &T) -> Option<C> + 'a,
+    ) -> impl Iterator<Item = (Point2, C)> + 'a {
+        let from = &self[*pos];
+        self.neighbours(pos).filter_map(move |n| cost(from, &self[n]).map(|c| (n, c)))
+    }
+
+    pub fn map<U>(&self, f: impl FnMut(&T) -> U) -> Grid<U> {
+        Grid::new(self.rows, self.cols, self.data.iter().map(f).collect())
+    }
+
+    pub fn render(&self, mut f: impl FnMut(&T) -> char) -> String {
+        self.data
+            .chunks(self.cols.max(1))
+            .map(|row| row.iter().map(&mut f).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl<T> Index<Point2> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, pos: Point2) -> &Self::Output {
+        self.get(&pos).unwrap()
+    }
+}
+
+impl<T> IndexMut<Point2> for Grid<T> {
+    fn index_mut(&mut self, pos: Point2) -> &mut Self::Output {
+        self.get_mut(&pos).unwrap()
+    }
+}
@@ -0,0 +1,318 @@
+use std::cmp::{max, min};
+use std::fmt;
+
+// Half-open [start, end), inclusive ranges are shifted on construction
+#[derive(Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Interval {
+    start: i64,
+    end: i64,
+}
+
+impl Interval {
+    pub fn exclusive(start: i64, end: i64) -> Self {
+        Self { start, end: max(start, end) }
+    }
+
+    // The end saturates, so a range up to i64::MAX leaves out i64::MAX itself
+    pub fn inclusive(first: i64, last: i64) -> Self {
+        Self::exclusive(first, last.saturating_add(1))
+    }
+
+    pub fn start(&self) -> i64 {
+        self.start
+    }
+
+    pub fn end(&self) -> i64 {
+        self.end
+    }
+
+    pub fn last(&self) -> i64 {
+        self.end - 1
+    }
+
+    pub fn len(&self) -> u64 {
+        self.end.wrapping_sub(self.start) as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start >= self.end
+    }
+
+    pub fn contains(&self, val: i64) -> bool {
+        self.start <= val && val < self.end
+    }
+
+    pub fn contains_interval(&self, other: &Interval) -> bool {
+        other.is_empty() || (self.start <= other.start && other.end <= self.end)
+    }
+
+    pub fn overlaps(&self, other: &Interval) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+
+    // Overlapping or directly adjacent, so the union is a single interval
+    pub fn touches(&self, other: &Interval) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    pub fn intersection(&self, other: &Interval) -> Option<Interval> {
+        let res = Self::exclusive(max(self.start, other.start), min(self.end, other.end));
+        if res.is_empty() {
+            None
+        } else {
+            Some(res)
+        }
+    }
+
+    pub fn union(&self, other: &Interval) -> Option<Interval> {
+        if self.is_empty() {
+            Some(*other)
+        } else if other.is_empty() {
+            Some(*self)
+        } else if self.touches(other) {
+            Some(Self::exclusive(min(self.start, other.start), max(self.end, other.end)))
+        } else {
+            None
+        }
+    }
+
+    // Up to two pieces of self left after cutting out other
+    pub fn subtract(&self, other: &Interval) -> impl Iterator<Item = Interval> {
+        let (left, right) = if self.overlaps(other) {
+            (
+                Self::exclusive(self.start, other.start),
+                Self::exclusive(other.end, self.end),
+            )
+        } else {
+            (*self, Self::exclusive(self.end, self.end))
+        };
+        [left, right].into_iter().filter(|i| !i.is_empty())
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{},{})", self.start, self.end)
+    }
+}
+
+// Sorted, disjoint and non-adjacent intervals
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct RangeSet {
+    ranges: Vec<Interval>,
+}
+
+impl RangeSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Interval> {
+        self.ranges.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    pub fn total_len(&self) -> u64 {
+        self.ranges.iter().map(Interval::len).sum()
+    }
+
+    pub fn span(&self) -> Option<Interval> {
+        let first = self.ranges.first()?;
+        let last = self.ranges.last()?;
+        Some(Interval::exclusive(first.start, last.end))
+    }
+
+    pub fn contains(&self, val: i64) -> bool {
+        let idx = self.ranges.partition_point(|r| r.end <= val);
+        self.ranges.get(idx).is_some_and(|r| r.contains(val))
+    }
+
+    pub fn contains_interval(&self, interval: &Interval) -> bool {
+        let idx = self.ranges.partition_point(|r| r.end <= interval.start);
+        interval.is_empty() || self.ranges.get(idx).is_some_and(|r| r.contains_interval(interval))
+    }
+
+    pub fn insert(&mut self, interval: Interval) {
+        if interval.is_empty() {
+            return;
+        }
+
+        // Everything between lo and hi touches the new interval and gets merged into it
+        let lo = self.ranges.partition_point(|r| r.end < interval.start);
+        let hi = self.ranges.partition_point(|r| r.start <= interval.end);
+        let merged = self.ranges[lo..hi]
+            .iter()
+            .fold(interval, |acc, r| acc.union(r).unwrap());
+        self.ranges.splice(lo..hi, [merged]);
+    }
+
+    pub fn remove(&mut self, interval: Interval) {
+        if interval.is_empty() {
+            return;
+        }
+
+        let lo = self.ranges.partition_point(|r| r.end <= interval.start);
+        let hi = self.ranges.partition_point(|r| r.start < interval.end);
+        let rest: Vec<Interval> = self.ranges[lo..hi]
+            .iter()
+            .flat_map(|r| r.subtract(&interval))
+            .collect();
+        self.ranges.splice(lo..hi, rest);
+    }
+
+    pub fn union(&self, other: &RangeSet) -> RangeSet {
+        let mut res = self.clone();
+        other.iter().for_each(|r| res.insert(*r));
+        res
+    }
+
+    pub fn intersection(&self, other: &RangeSet) -> RangeSet {
+        let mut ranges = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while let (Some(a), Some(b)) = (self.ranges.get(i), other.ranges.get(j)) {
+            if let Some(common) = a.intersection(b) {
+                ranges.push(common);
+            }
+            if a.end < b.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        RangeSet { ranges }
+    }
+
+    pub fn subtract(&self, other: &RangeSet) -> RangeSet {
+        let mut res = self.clone();
+        other.iter().for_each(|r| res.remove(*r));
+        res
+    }
+
+    // Uncovered holes between the first and last range
+    pub fn gaps(&self) -> impl Iterator<Item = Interval> + '_ {
+        self.ranges
+            .windows(2)
+            .map(|pair| Interval::exclusive(pair[0].end, pair[1].start))
+    }
+
+    pub fn gaps_within(&self, bounds: &Interval) -> RangeSet {
+        RangeSet::from(*bounds).subtract(self)
+    }
+
+    pub fn clamp(&self, bounds: &Interval) -> RangeSet {
+        self.intersection(&RangeSet::from(*bounds))
+    }
+}
+
+impl From<Interval> for RangeSet {
+    fn from(interval: Interval) -> Self {
+        let mut res = RangeSet::new();
+        res.insert(interval);
+        res
+    }
+}
+
+impl FromIterator<Interval> for RangeSet {
+    fn from_iter<I: IntoIterator<Item = Interval>>(iter: I) -> Self {
+        let mut ranges: Vec<Interval> = iter.into_iter().filter(|i| !i.is_empty()).collect();
+        ranges.sort_unstable();
+
+        let mut merged: Vec<Interval> = Vec::with_capacity(ranges.len());
+        for r in ranges {
+            match merged.last_mut() {
+                Some(last) if last.touches(&r) => *last = last.union(&r).unwrap(),
+                _ => merged.push(r),
+            }
+        }
+        RangeSet { ranges: merged }
+    }
+}
+
+impl Extend<Interval> for RangeSet {
+    fn extend<I: IntoIterator<Item = Interval>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|r| self.insert(r));
+    }
+}
+
+impl fmt::Display for RangeSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ranges: Vec<String> = self.ranges.iter().map(|r| r.to_string()).collect();
+        write!(f, "{{{}}}", ranges.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(ranges: &[(i64, i64)]) -> RangeSet {
+        let mut res = RangeSet::new();
+        ranges.iter().for_each(|(start, end)| res.insert(Interval::exclusive(*start, *end)));
+        res
+    }
+
+    fn ranges(set: &RangeSet) -> Vec<(i64, i64)> {
+        set.iter().map(|r| (r.start(), r.end())).collect()
+    }
+
+    #[test]
+    fn inclusive_saturates_at_the_top() {
+        let top = Interval::inclusive(i64::MAX - 2, i64::MAX);
+        assert_eq!((top.start(), top.end(), top.len()), (i64::MAX - 2, i64::MAX, 2));
+        assert_eq!(Interval::inclusive(i64::MIN, i64::MAX).len(), u64::MAX);
+        assert_eq!(Interval::inclusive(3, 5), Interval::exclusive(3, 6));
+    }
+
+    #[test]
+    fn insert_merges_adjacent_and_overlapping() {
+        assert_eq!(ranges(&set(&[(0, 5), (5, 10)])), vec![(0, 10)]);
+        assert_eq!(ranges(&set(&[(5, 10), (0, 5)])), vec![(0, 10)]);
+        assert_eq!(ranges(&set(&[(0, 4), (6, 10)])), vec![(0, 4), (6, 10)]);
+        assert_eq!(ranges(&set(&[(0, 4), (6, 10), (4, 6)])), vec![(0, 10)]);
+        assert_eq!(ranges(&set(&[(0, 4), (6, 10), (3, 7)])), vec![(0, 10)]);
+        assert_eq!(ranges(&set(&[(0, 4), (6, 10), (12, 14), (-5, 20)])), vec![(-5, 20)]);
+        assert_eq!(ranges(&set(&[(0, 10), (2, 4)])), vec![(0, 10)]);
+        assert_eq!(ranges(&set(&[(0, 4), (8, 8), (6, 6)])), vec![(0, 4)]);
+    }
+
+    #[test]
+    fn remove_cuts_pieces_out() {
+        let mut s = set(&[(0, 10), (20, 30)]);
+        s.remove(Interval::exclusive(10, 20));
+        assert_eq!(ranges(&s), vec![(0, 10), (20, 30)]);
+        s.remove(Interval::exclusive(3, 5));
+        assert_eq!(ranges(&s), vec![(0, 3), (5, 10), (20, 30)]);
+        s.remove(Interval::exclusive(8, 25));
+        assert_eq!(ranges(&s), vec![(0, 3), (5, 8), (25, 30)]);
+        s.remove(Interval::exclusive(7, 7));
+        assert_eq!(ranges(&s), vec![(0, 3), (5, 8), (25, 30)]);
+        s.remove(Interval::exclusive(-10, 40));
+        assert!(s.is_empty());
+    }
+
+    #[test]
+    fn intersection_keeps_common_parts() {
+        let a = set(&[(0, 5), (10, 15), (20, 25)]);
+        assert_eq!(ranges(&a.intersection(&set(&[(5, 10)]))), vec![]);
+        assert_eq!(ranges(&a.intersection(&set(&[(3, 12), (14, 30)]))), vec![(3, 5), (10, 12), (14, 15), (20, 25)]);
+        assert_eq!(ranges(&a.intersection(&set(&[(-1, 100)]))), ranges(&a));
+        assert_eq!(ranges(&a.intersection(&RangeSet::new())), vec![]);
+    }
+
+    #[test]
+    fn gaps_within_bounds() {
+        let a = set(&[(0, 5), (10, 15)]);
+        assert_eq!(ranges(&a.gaps_within(&Interval::exclusive(-3, 20))), vec![(-3, 0), (5, 10), (15, 20)]);
+        assert_eq!(ranges(&a.gaps_within(&Interval::exclusive(1, 4))), vec![]);
+        assert_eq!(ranges(&a.gaps_within(&Interval::exclusive(5, 10))), vec![(5, 10)]);
+        assert_eq!(ranges(&a.gaps_within(&Interval::exclusive(7, 7))), vec![]);
+        assert_eq!(a.gaps().collect::<Vec<_>>(), vec![Interval::exclusive(5, 10)]);
+    }
+}
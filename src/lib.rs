@@ -1,4 +1,10 @@
 #[macro_use]
 pub mod common;
+pub mod automaton;
 pub mod days;
+pub mod grid;
+pub mod interval;
+pub mod ocr;
 pub mod points;
+pub mod render;
+pub mod search;
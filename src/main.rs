@@ -1,6 +1,4 @@
-mod common;
-mod days;
-mod points;
+use rustaocean2022::{common, days};
 
 fn main() {
     for day in days::all_numbers() {
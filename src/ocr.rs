@@ -0,0 +1,169 @@
+use std::fmt;
+
+use crate::grid::Grid;
+
+// The 4x6 font of CRT-style puzzles, glyphs are stored without their spacing column
+const SMALL_FONT: [(char, &str); 18] = [
+    ('A', ".##.\n#..#\n#..#\n####\n#..#\n#..#"),
+    ('B', "###.\n#..#\n###.\n#..#\n#..#\n###."),
+    ('C', ".##.\n#..#\n#...\n#...\n#..#\n.##."),
+    ('E', "####\n#...\n###.\n#...\n#...\n####"),
+    ('F', "####\n#...\n###.\n#...\n#...\n#..."),
+    ('G', ".##.\n#..#\n#...\n#.##\n#..#\n.###"),
+    ('H', "#..#\n#..#\n####\n#..#\n#..#\n#..#"),
+    ('I', "###\n.#.\n.#.\n.#.\n.#.\n###"),
+    ('J', "..##\n...#\n...#\n...#\n#..#\n.##."),
+    ('K', "#..#\n#.#.\n##..\n#.#.\n#.#.\n#..#"),
+    ('L', "#...\n#...\n#...\n#...\n#...\n####"),
+    ('O', ".##.\n#..#\n#..#\n#..#\n#..#\n.##."),
+    ('P', "###.\n#..#\n#..#\n###.\n#...\n#..."),
+    ('R', "###.\n#..#\n#..#\n###.\n#.#.\n#..#"),
+    ('S', ".###\n#...\n#...\n.##.\n...#\n###."),
+    ('U', "#..#\n#..#\n#..#\n#..#\n#..#\n.##."),
+    ('Y', "#...#\n#...#\n.#.#.\n..#..\n..#..\n..#.."),
+    ('Z', "####\n...#\n..#.\n.#..\n#...\n####"),
+];
+
+// The 6x10 font of the moving-stars puzzles
+const LARGE_FONT: [(char, &str); 15] = [
+    ('A', "..##..\n.#..#.\n#....#\n#....#\n#....#\n######\n#....#\n#....#\n#....#\n#....#"),
+    ('B', "#####.\n#....#\n#....#\n#....#\n#####.\n#....#\n#....#\n#....#\n#....#\n#####."),
+    ('C', ".####.\n#....#\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#....#\n.####."),
+    ('E', "######\n#.....\n#.....\n#.....\n#####.\n#.....\n#.....\n#.....\n#.....\n######"),
+    ('F', "######\n#.....\n#.....\n#.....\n#####.\n#.....\n#.....\n#.....\n#.....\n#....."),
+    ('G', ".####.\n#....#\n#.....\n#.....\n#.....\n#..###\n#....#\n#....#\n#...##\n.###.#"),
+    ('H', "#....#\n#....#\n#....#\n#....#\n######\n#....#\n#....#\n#....#\n#....#\n#....#"),
+    ('J', "...###\n....#.\n....#.\n....#.\n....#.\n....#.\n....#.\n#...#.\n#...#.\n.###.."),
+    ('K', "#....#\n#...#.\n#..#..\n#.#...\n##....\n##....\n#.#...\n#..#..\n#...#.\n#....#"),
+    ('L', "#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n######"),
+    ('N', "#....#\n##...#\n##...#\n#.#..#\n#.#..#\n#..#.#\n#..#.#\n#...##\n#...##\n#....#"),
+    ('P', "#####.\n#....#\n#....#\n#....#\n#####.\n#.....\n#.....\n#.....\n#.....\n#....."),
+    ('R', "#####.\n#....#\n#....#\n#....#\n#####.\n#..#..\n#...#.\n#...#.\n#....#\n#....#"),
+    ('X', "#....#\n#....#\n.#..#.\n.#..#.\n..##..\n..##..\n.#..#.\n.#..#.\n#....#\n#....#"),
+    ('Z', "######\n.....#\n.....#\n....#.\n...#..\n..#...\n.#....\n#.....\n#.....\n######"),
+];
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum OcrError {
+    UnsupportedHeight(usize),
+    RaggedRows,
+    UnknownGlyph { index: usize, column: usize, glyph: String },
+}
+
+impl fmt::Display for OcrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OcrError::UnsupportedHeight(h) => write!(f, "no font with glyphs {} pixels high", h),
+            OcrError::RaggedRows => write!(f, "pixel rows differ in length"),
+            OcrError::UnknownGlyph { index, column, glyph } => {
+                write!(f, "unknown glyph #{} at column {}:\n{}", index, column, glyph)
+            }
+        }
+    }
+}
+
+impl std::error::Error for OcrError {}
+
+fn font_for(height: usize) -> Option<&'static [(char, &'static str)]> {
+    match height {
+        6 => Some(&SMALL_FONT),
+        10 => Some(&LARGE_FONT),
+        _ => None,
+    }
+}
+
+// Letters are told apart by the fully dark columns between them
+pub fn read_rows<R: AsRef<[bool]>>(rows: &[R]) -> Result<String, OcrError> {
+    let font = font_for(rows.len()).ok_or(OcrError::UnsupportedHeight(rows.len()))?;
+    let width = rows.first().map_or(0, |row| row.as_ref().len());
+    if rows.iter().any(|row| row.as_ref().len() != width) {
+        return Err(OcrError::RaggedRows);
+    }
+
+    let lit_column = |x: usize| rows.iter().any(|row| row.as_ref()[x]);
+    let mut result = String::new();
+    let mut x = 0;
+    while x < width {
+        if !lit_column(x) {
+            x += 1;
+            continue;
+        }
+
+        let start = x;
+        while x < width && lit_column(x) {
+            x += 1;
+        }
+        let glyph = rows
+            .iter()
+            .map(|row| row.as_ref()[start..x].iter().map(|&lit| if lit { '#' } else { '.' }).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        match font.iter().find(|(_, pattern)| *pattern == glyph) {
+            Some((letter, _)) => result.push(*letter),
+            None => {
+                return Err(OcrError::UnknownGlyph {
+                    index: result.len(),
+                    column: start,
+                    glyph,
+                })
+            }
+        }
+    }
+    Ok(result)
+}
+
+pub fn read_grid(grid: &Grid<bool>) -> Result<String, OcrError> {
+    let rows: Vec<&[bool]> = (0..grid.rows()).map(|y| grid.row(y)).collect();
+    read_rows(&rows)
+}
+
+// Reads a picture drawn with '#' for lit pixels, any other character is dark
+pub fn read_text(picture: &str) -> Result<String, OcrError> {
+    let rows: Vec<Vec<bool>> = picture
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.chars().map(|c| c == '#').collect())
+        .collect();
+    read_rows(&rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_small_font() {
+        let picture = ".##..###.\n#..#.#..#\n#..#.###.\n####.#..#\n#..#.#..#\n#..#.###.";
+        assert_eq!(read_text(picture), Ok("AB".to_string()));
+    }
+
+    #[test]
+    fn reads_large_font() {
+        let (_, z) = LARGE_FONT.iter().find(|(c, _)| *c == 'Z').unwrap();
+        assert_eq!(read_text(z), Ok("Z".to_string()));
+    }
+
+    #[test]
+    fn reads_grid() {
+        let lines: Vec<String> = "#..#\n#..#\n####\n#..#\n#..#\n#..#".lines().map(String::from).collect();
+        let grid = Grid::from_lines(&lines, |c| c == '#');
+        assert_eq!(read_grid(&grid), Ok("H".to_string()));
+    }
+
+    #[test]
+    fn reports_unknown_glyph() {
+        let picture = "####.#\n#....#\n###..#\n#....#\n#....#\n####.#";
+        let err = read_text(picture).unwrap_err();
+        assert_eq!(
+            err,
+            OcrError::UnknownGlyph { index: 1, column: 5, glyph: "#\n#\n#\n#\n#\n#".to_string() }
+        );
+        assert!(err.to_string().starts_with("unknown glyph #1 at column 5"));
+    }
+
+    #[test]
+    fn reports_unsupported_height() {
+        assert_eq!(read_text("#\n#"), Err(OcrError::UnsupportedHeight(2)));
+    }
+}
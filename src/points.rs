@@ -1,63 +1,403 @@
-use itertools::iproduct;
+use std::fmt;
+use std::ops::{Add, AddAssign, Index, IndexMut, Mul, Neg, Sub, SubAssign};
+use std::str::FromStr;
 
 pub trait Point: Sized {
     fn get_neighbours(&self) -> Vec<Self>;
-    fn new(from: (i32, i32, i32, i32)) -> Self;
+
+    fn for_each_neighbour(&self, f: impl FnMut(Self)) {
+        self.get_neighbours().into_iter().for_each(f)
+    }
 }
 
-#[derive(Copy, Clone, Hash, PartialEq, Eq)]
-pub struct Point3 {
-    x: i32,
-    y: i32,
-    z: i32,
+#[derive(Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct PointN<const D: usize> {
+    coords: [i32; D],
 }
 
-impl Point for Point3 {
-    fn new(from: (i32, i32, i32, i32)) -> Self {
+pub type Point2 = PointN<2>;
+pub type Point3 = PointN<3>;
+pub type Point4 = PointN<4>;
+
+impl<const D: usize> PointN<D> {
+    pub const fn new(coords: [i32; D]) -> Self {
+        Self { coords }
+    }
+
+    pub const fn origin() -> Self {
+        Self { coords: [0; D] }
+    }
+
+    pub fn coords(&self) -> [i32; D] {
+        self.coords
+    }
+
+    pub fn dim(&self) -> usize {
+        D
+    }
+
+    // Unit steps along each axis, in both directions: 2*D points
+    pub fn orthogonal_neighbours(&self) -> impl Iterator<Item = Self> {
+        let origin = self.coords;
+        (0..2 * D).map(move |i| {
+            let mut coords = origin;
+            coords[i / 2] += if i % 2 == 0 { -1 } else { 1 };
+            Self { coords }
+        })
+    }
+
+    // Every point with each coordinate differing by at most 1: 3^D - 1 points
+    pub fn moore_neighbours(&self) -> impl Iterator<Item = Self> {
+        let origin = self.coords;
+        let total = 3usize.pow(D as u32);
+        let center = total / 2;
+        (0..total).filter(move |&i| i != center).map(move |mut i| {
+            let mut coords = origin;
+            for c in coords.iter_mut() {
+                *c += (i % 3) as i32 - 1;
+                i /= 3;
+            }
+            Self { coords }
+        })
+    }
+
+    pub fn manhattan(&self, other: &Self) -> i32 {
+        self.coords
+            .iter()
+            .zip(other.coords.iter())
+            .map(|(a, b)| (a - b).abs())
+            .sum()
+    }
+
+    pub fn chebyshev(&self, other: &Self) -> i32 {
+        self.coords
+            .iter()
+            .zip(other.coords.iter())
+            .map(|(a, b)| (a - b).abs())
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn euclidean_sq(&self, other: &Self) -> i64 {
+        self.coords
+            .iter()
+            .zip(other.coords.iter())
+            .map(|(a, b)| (*a as i64 - *b as i64).pow(2))
+            .sum()
+    }
+
+    pub fn signum(&self) -> Self {
         Self {
-            x: from.0,
-            y: from.1,
-            z: from.2,
+            coords: self.coords.map(i32::signum),
         }
     }
 
+    pub fn map(&self, f: impl FnMut(i32) -> i32) -> Self {
+        Self {
+            coords: self.coords.map(f),
+        }
+    }
+
+    pub fn zip_with(&self, other: &Self, mut f: impl FnMut(i32, i32) -> i32) -> Self {
+        let mut coords = self.coords;
+        coords
+            .iter_mut()
+            .zip(other.coords.iter())
+            .for_each(|(a, b)| *a = f(*a, *b));
+        Self { coords }
+    }
+
+    pub fn component_min(&self, other: &Self) -> Self {
+        self.zip_with(other, i32::min)
+    }
+
+    pub fn component_max(&self, other: &Self) -> Self {
+        self.zip_with(other, i32::max)
+    }
+}
+
+impl PointN<2> {
+    pub const fn xy(x: i32, y: i32) -> Self {
+        Self::new([x, y])
+    }
+
+    pub fn x(&self) -> i32 {
+        self.coords[0]
+    }
+
+    pub fn y(&self) -> i32 {
+        self.coords[1]
+    }
+}
+
+impl PointN<3> {
+    pub const fn xyz(x: i32, y: i32, z: i32) -> Self {
+        Self::new([x, y, z])
+    }
+}
+
+impl PointN<4> {
+    pub const fn xyzw(x: i32, y: i32, z: i32, w: i32) -> Self {
+        Self::new([x, y, z, w])
+    }
+}
+
+impl<const D: usize> Point for PointN<D> {
     fn get_neighbours(&self) -> Vec<Self> {
-        let xs = (self.x - 1)..=(self.x + 1);
-        let ys = (self.y - 1)..=(self.y + 1);
-        let zs = (self.z - 1)..=(self.z + 1);
-        iproduct!(xs, ys, zs)
-            .map(|(x, y, z)| Self::new((x, y, z, 0)))
-            .filter(|p| self != p)
-            .collect()
+        self.moore_neighbours().collect()
+    }
+
+    fn for_each_neighbour(&self, f: impl FnMut(Self)) {
+        self.moore_neighbours().for_each(f)
     }
 }
 
-#[derive(Copy, Clone, Hash, PartialEq, Eq)]
-pub struct Point4 {
-    x: i32,
-    y: i32,
-    z: i32,
-    w: i32,
+impl<const D: usize> Default for PointN<D> {
+    fn default() -> Self {
+        Self::origin()
+    }
 }
 
-impl Point for Point4 {
-    fn new(from: (i32, i32, i32, i32)) -> Self {
-        Self {
-            x: from.0,
-            y: from.1,
-            z: from.2,
-            w: from.3,
+impl<const D: usize> From<[i32; D]> for PointN<D> {
+    fn from(coords: [i32; D]) -> Self {
+        Self { coords }
+    }
+}
+
+impl<const D: usize> Index<usize> for PointN<D> {
+    type Output = i32;
+
+    fn index(&self, idx: usize) -> &Self::Output {
+        &self.coords[idx]
+    }
+}
+
+impl<const D: usize> IndexMut<usize> for PointN<D> {
+    fn index_mut(&mut self, idx: usize) -> &mut Self::Output {
+        &mut self.coords[idx]
+    }
+}
+
+impl<const D: usize> Add for PointN<D> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.zip_with(&rhs, |a, b| a + b)
+    }
+}
+
+impl<const D: usize> Sub for PointN<D> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.zip_with(&rhs, |a, b| a - b)
+    }
+}
+
+impl<const D: usize> Mul<i32> for PointN<D> {
+    type Output = Self;
+
+    fn mul(self, rhs: i32) -> Self::Output {
+        self.map(|a| a * rhs)
+    }
+}
+
+impl<const D: usize> Neg for PointN<D> {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        self.map(|a| -a)
+    }
+}
+
+impl<const D: usize> AddAssign for PointN<D> {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl<const D: usize> SubAssign for PointN<D> {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl<const D: usize> fmt::Display for PointN<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[")?;
+        for (i, c) in self.coords.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", c)?;
+        }
+        write!(f, "]")
+    }
+}
+
+#[derive(Copy, Clone, Hash, PartialEq, Eq, Debug)]
+pub enum HexDirection {
+    East,
+    SouthEast,
+    SouthWest,
+    West,
+    NorthWest,
+    NorthEast,
+}
+
+impl HexDirection {
+    pub const ALL: [HexDirection; 6] = [
+        HexDirection::East,
+        HexDirection::SouthEast,
+        HexDirection::SouthWest,
+        HexDirection::West,
+        HexDirection::NorthWest,
+        HexDirection::NorthEast,
+    ];
+
+    pub fn offset(&self) -> Hex {
+        match self {
+            HexDirection::East => Hex::new(1, 0),
+            HexDirection::SouthEast => Hex::new(0, 1),
+            HexDirection::SouthWest => Hex::new(-1, 1),
+            HexDirection::West => Hex::new(-1, 0),
+            HexDirection::NorthWest => Hex::new(0, -1),
+            HexDirection::NorthEast => Hex::new(1, -1),
+        }
+    }
+
+    // Splits an undelimited walk like "nwwswee" into its steps
+    pub fn parse_path(s: &str) -> Option<Vec<HexDirection>> {
+        let mut path = Vec::new();
+        let mut rest = s.trim();
+        while !rest.is_empty() {
+            let len = if rest.starts_with('n') || rest.starts_with('s') { 2 } else { 1 };
+            path.push(rest.get(..len)?.parse().ok()?);
+            rest = &rest[len..];
+        }
+        Some(path)
+    }
+}
+
+impl FromStr for HexDirection {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "e" => Ok(HexDirection::East),
+            "se" => Ok(HexDirection::SouthEast),
+            "sw" => Ok(HexDirection::SouthWest),
+            "w" => Ok(HexDirection::West),
+            "nw" => Ok(HexDirection::NorthWest),
+            "ne" => Ok(HexDirection::NorthEast),
+            _ => Err(()),
+        }
+    }
+}
+
+// Axial coordinates of a pointy-top hex grid, the cube coordinate s is derived
+#[derive(Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub struct Hex {
+    q: i32,
+    r: i32,
+}
+
+impl Hex {
+    pub const fn new(q: i32, r: i32) -> Self {
+        Self { q, r }
+    }
+
+    pub fn from_cube(q: i32, r: i32, s: i32) -> Option<Self> {
+        if q + r + s == 0 {
+            Some(Self { q, r })
+        } else {
+            None
         }
     }
 
+    pub fn q(&self) -> i32 {
+        self.q
+    }
+
+    pub fn r(&self) -> i32 {
+        self.r
+    }
+
+    pub fn s(&self) -> i32 {
+        -self.q - self.r
+    }
+
+    pub fn cube(&self) -> [i32; 3] {
+        [self.q, self.r, self.s()]
+    }
+
+    pub fn step(&self, dir: HexDirection) -> Self {
+        *self + dir.offset()
+    }
+
+    pub fn walk(&self, path: &[HexDirection]) -> Self {
+        path.iter().fold(*self, |hex, dir| hex.step(*dir))
+    }
+
+    pub fn neighbours(&self) -> impl Iterator<Item = Self> {
+        let origin = *self;
+        HexDirection::ALL.iter().map(move |dir| origin.step(*dir))
+    }
+
+    pub fn distance(&self, other: &Self) -> i32 {
+        let diff = *self - *other;
+        (diff.q.abs() + diff.r.abs() + diff.s().abs()) / 2
+    }
+
+    // Axial coordinates sheared into a square grid, so neighbours stay within chebyshev distance 1
+    pub fn to_point(self) -> Point2 {
+        Point2::xy(self.q, self.r)
+    }
+}
+
+impl Point for Hex {
     fn get_neighbours(&self) -> Vec<Self> {
-        let xs = (self.x - 1)..=(self.x + 1);
-        let ys = (self.y - 1)..=(self.y + 1);
-        let zs = (self.z - 1)..=(self.z + 1);
-        let ws = (self.w - 1)..=(self.w + 1);
-        iproduct!(xs, ys, zs, ws)
-            .map(Self::new)
-            .filter(|p| self != p)
-            .collect()
+        self.neighbours().collect()
+    }
+
+    fn for_each_neighbour(&self, f: impl FnMut(Self)) {
+        self.neighbours().for_each(f)
+    }
+}
+
+impl FromStr for Hex {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let path = HexDirection::parse_path(s).ok_or(())?;
+        Ok(Hex::default().walk(&path))
+    }
+}
+
+impl From<Hex> for Point2 {
+    fn from(hex: Hex) -> Self {
+        hex.to_point()
+    }
+}
+
+impl Add for Hex {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::new(self.q + rhs.q, self.r + rhs.r)
+    }
+}
+
+impl Sub for Hex {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::new(self.q - rhs.q, self.r - rhs.r)
+    }
+}
+
+impl fmt::Display for Hex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{},{},{}]", self.q, self.r, self.s())
     }
 }
@@ -0,0 +1,154 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::grid::Grid;
+
+pub type Rgb = [u8; 3];
+
+pub const BLACK: Rgb = [0, 0, 0];
+pub const WHITE: Rgb = [255, 255, 255];
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgb>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, fill: Rgb) -> Self {
+        Self { width, height, pixels: vec![fill; width * height] }
+    }
+
+    // Every grid cell becomes a scale x scale square
+    pub fn from_grid<T>(grid: &Grid<T>, scale: usize, mut color: impl FnMut(&T) -> Rgb) -> Self {
+        let mut image = Self::new(grid.cols() * scale, grid.rows() * scale, BLACK);
+        for (pos, val) in grid.iter() {
+            image.fill_cell(pos.x() as usize, pos.y() as usize, scale, color(val));
+        }
+        image
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<Rgb> {
+        if x < self.width && y < self.height {
+            Some(self.pixels[y * self.width + x])
+        } else {
+            None
+        }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: Rgb) {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x] = color;
+        }
+    }
+
+    // Paints the scale x scale block that cell (x, y) of a grid is drawn as
+    pub fn fill_cell(&mut self, x: usize, y: usize, scale: usize, color: Rgb) {
+        for dy in 0..scale {
+            for dx in 0..scale {
+                self.set(x * scale + dx, y * scale + dy, color);
+            }
+        }
+    }
+
+    pub fn write_ppm(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "P6\n{} {}\n255\n", self.width, self.height)?;
+        out.write_all(&self.pixels.concat())
+    }
+
+    // Truecolor PNG with uncompressed (stored) deflate blocks, so no codec is needed
+    pub fn write_png(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(b"\x89PNG\r\n\x1a\n")?;
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height as u32).to_be_bytes());
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+        write_png_chunk(out, b"IHDR", &header)?;
+
+        let mut raw = Vec::with_capacity(self.height * (1 + 3 * self.width));
+        for row in self.pixels.chunks(self.width.max(1)) {
+            raw.push(0);
+            row.iter().for_each(|px| raw.extend_from_slice(px));
+        }
+        write_png_chunk(out, b"IDAT", &zlib_stored(&raw))?;
+        write_png_chunk(out, b"IEND", &[])
+    }
+
+    // Picks the format from the file extension, PPM unless it is .png
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("png") => self.write_png(&mut out)?,
+            _ => self.write_ppm(&mut out)?,
+        }
+        out.flush()
+    }
+}
+
+fn write_png_chunk(out: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+    let crc = crc32(kind.iter().chain(data.iter()));
+    out.write_all(&crc.to_be_bytes())
+}
+
+fn crc32<'a>(bytes: impl Iterator<Item = &'a u8>) -> u32 {
+    !bytes.fold(!0u32, |crc, b| {
+        (0..8).fold(crc ^ *b as u32, |c, _| if c & 1 == 1 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 })
+    })
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(is_last as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+
+    let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), byte| {
+        let a = (a + *byte as u32) % 65521;
+        (a, (b + a) % 65521)
+    });
+    out.extend_from_slice(&((b << 16) | a).to_be_bytes());
+    out
+}
+
+// Packs two pixel rows into each line of text using half-block characters
+pub fn render_blocks(grid: &Grid<bool>) -> String {
+    (0..grid.rows())
+        .step_by(2)
+        .map(|y| {
+            let upper = grid.row(y);
+            let lower = if y + 1 < grid.rows() { Some(grid.row(y + 1)) } else { None };
+            (0..grid.cols())
+                .map(|x| match (upper[x], lower.is_some_and(|row| row[x])) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,295 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::hash::Hash;
+use std::ops::Add;
+
+pub trait Cost: Copy + Ord + Add<Output = Self> + Default {}
+
+impl<C: Copy + Ord + Add<Output = C> + Default> Cost for C {}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Path<N, C> {
+    pub cost: C,
+    pub nodes: Vec<N>,
+}
+
+impl<N, C> Path<N, C> {
+    pub fn start(&self) -> Option<&N> {
+        self.nodes.first()
+    }
+
+    pub fn end(&self) -> Option<&N> {
+        self.nodes.last()
+    }
+}
+
+// Distances and back-links of every node reached by a search
+#[derive(Clone, Debug)]
+pub struct SearchTree<N, C> {
+    pub dist: HashMap<N, C>,
+    parents: HashMap<N, N>,
+}
+
+impl<N: Clone + Eq + Hash, C: Copy> SearchTree<N, C> {
+    fn new() -> Self {
+        Self {
+            dist: HashMap::new(),
+            parents: HashMap::new(),
+        }
+    }
+
+    pub fn distance(&self, node: &N) -> Option<C> {
+        self.dist.get(node).copied()
+    }
+
+    pub fn path_to(&self, goal: &N) -> Option<Path<N, C>> {
+        let cost = self.distance(goal)?;
+        let mut nodes = vec![goal.clone()];
+        while let Some(parent) = self.parents.get(nodes.last().unwrap()) {
+            nodes.push(parent.clone());
+        }
+        nodes.reverse();
+        Some(Path { cost, nodes })
+    }
+}
+
+// Visits nodes in waves of equal step count, starting from all of `starts` at once.
+// Stops at the first node accepted by `is_goal`, which is returned alongside the tree.
+pub fn bfs_tree<N, I>(
+    starts: impl IntoIterator<Item = N>,
+    mut neighbours: impl FnMut(&N) -> I,
+    mut is_goal: impl FnMut(&N) -> bool,
+) -> (SearchTree<N, usize>, Option<N>)
+where
+    N: Clone + Eq + Hash,
+    I: IntoIterator<Item = N>,
+{
+    let mut tree = SearchTree::new();
+    let mut queue = VecDeque::new();
+    for start in starts {
+        if !tree.dist.contains_key(&start) {
+            tree.dist.insert(start.clone(), 0);
+            queue.push_back(start);
+        }
+    }
+
+    while let Some(node) = queue.pop_front() {
+        if is_goal(&node) {
+            return (tree, Some(node));
+        }
+
+        let steps = tree.dist[&node] + 1;
+        for next in neighbours(&node) {
+            if !tree.dist.contains_key(&next) {
+                tree.dist.insert(next.clone(), steps);
+                tree.parents.insert(next.clone(), node.clone());
+                queue.push_back(next);
+            }
+        }
+    }
+    (tree, None)
+}
+
+pub fn bfs<N, I>(
+    starts: impl IntoIterator<Item = N>,
+    neighbours: impl FnMut(&N) -> I,
+    is_goal: impl FnMut(&N) -> bool,
+) -> Option<Path<N, usize>>
+where
+    N: Clone + Eq + Hash,
+    I: IntoIterator<Item = N>,
+{
+    let (tree, goal) = bfs_tree(starts, neighbours, is_goal);
+    tree.path_to(&goal?)
+}
+
+pub fn bfs_distances<N, I>(starts: impl IntoIterator<Item = N>, neighbours: impl FnMut(&N) -> I) -> HashMap<N, usize>
+where
+    N: Clone + Eq + Hash,
+    I: IntoIterator<Item = N>,
+{
+    bfs_tree(starts, neighbours, |_| false).0.dist
+}
+
+struct Entry<N, C> {
+    priority: C,
+    cost: C,
+    node: N,
+}
+
+impl<N, C: Ord> PartialEq for Entry<N, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority
+    }
+}
+
+impl<N, C: Ord> Eq for Entry<N, C> {}
+
+impl<N, C: Ord> PartialOrd for Entry<N, C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Reversed, so the max-heap pops the lowest priority first
+impl<N, C: Ord> Ord for Entry<N, C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.priority.cmp(&self.priority)
+    }
+}
+
+// A* over weighted edges; with a zero heuristic this is plain Dijkstra.
+// The heuristic has to be consistent for the returned costs to be optimal.
+pub fn astar_tree<N, C, I>(
+    starts: impl IntoIterator<Item = N>,
+    mut neighbours: impl FnMut(&N) -> I,
+    mut heuristic: impl FnMut(&N) -> C,
+    mut is_goal: impl FnMut(&N) -> bool,
+) -> (SearchTree<N, C>, Option<N>)
+where
+    N: Clone + Eq + Hash,
+    C: Cost,
+    I: IntoIterator<Item = (N, C)>,
+{
+    let mut tree = SearchTree::new();
+    let mut heap = BinaryHeap::new();
+    for start in starts {
+        tree.dist.insert(start.clone(), C::default());
+        heap.push(Entry {
+            priority: heuristic(&start),
+            cost: C::default(),
+            node: start,
+        });
+    }
+
+    while let Some(Entry { cost, node, .. }) = heap.pop() {
+        if tree.dist.get(&node).is_some_and(|best| *best < cost) {
+            continue;
+        }
+        if is_goal(&node) {
+            return (tree, Some(node));
+        }
+
+        for (next, weight) in neighbours(&node) {
+            let next_cost = cost + weight;
+            if tree.dist.get(&next).is_none_or(|best| next_cost < *best) {
+                tree.dist.insert(next.clone(), next_cost);
+                tree.parents.insert(next.clone(), node.clone());
+                heap.push(Entry {
+                    priority: next_cost + heuristic(&next),
+                    cost: next_cost,
+                    node: next,
+                });
+            }
+        }
+    }
+    (tree, None)
+}
+
+pub fn astar<N, C, I>(
+    starts: impl IntoIterator<Item = N>,
+    neighbours: impl FnMut(&N) -> I,
+    heuristic: impl FnMut(&N) -> C,
+    is_goal: impl FnMut(&N) -> bool,
+) -> Option<Path<N, C>>
+where
+    N: Clone + Eq + Hash,
+    C: Cost,
+    I: IntoIterator<Item = (N, C)>,
+{
+    let (tree, goal) = astar_tree(starts, neighbours, heuristic, is_goal);
+    tree.path_to(&goal?)
+}
+
+pub fn dijkstra<N, C, I>(
+    starts: impl IntoIterator<Item = N>,
+    neighbours: impl FnMut(&N) -> I,
+    is_goal: impl FnMut(&N) -> bool,
+) -> Option<Path<N, C>>
+where
+    N: Clone + Eq + Hash,
+    C: Cost,
+    I: IntoIterator<Item = (N, C)>,
+{
+    astar(starts, neighbours, |_| C::default(), is_goal)
+}
+
+pub fn dijkstra_distances<N, C, I>(starts: impl IntoIterator<Item = N>, neighbours: impl FnMut(&N) -> I) -> HashMap<N, C>
+where
+    N: Clone + Eq + Hash,
+    C: Cost,
+    I: IntoIterator<Item = (N, C)>,
+{
+    astar_tree(starts, neighbours, |_| C::default(), |_| false).0.dist
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+    use crate::points::Point2;
+
+    const EDGES: [(char, char, u32); 9] = [
+        ('a', 'b', 7), ('a', 'c', 9), ('a', 'f', 14), ('b', 'c', 10), ('b', 'd', 15),
+        ('c', 'd', 11), ('c', 'f', 2), ('d', 'e', 6), ('e', 'f', 9),
+    ];
+
+    fn edges(node: &char) -> Vec<(char, u32)> {
+        EDGES
+            .iter()
+            .filter_map(|(a, b, w)| match (*a == *node, *b == *node) {
+                (true, _) => Some((*b, *w)),
+                (_, true) => Some((*a, *w)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn dijkstra_finds_cheapest_path() {
+        let path = dijkstra(['a'], edges, |n| *n == 'e').unwrap();
+        assert_eq!(path, Path { cost: 20, nodes: vec!['a', 'c', 'f', 'e'] });
+        assert_eq!(dijkstra(['a'], edges, |n| *n == 'x'), None);
+    }
+
+    #[test]
+    fn dijkstra_starts_from_every_source() {
+        let path = dijkstra(['b', 'f'], edges, |n| *n == 'e').unwrap();
+        assert_eq!(path, Path { cost: 9, nodes: vec!['f', 'e'] });
+
+        let dist = dijkstra_distances(['a', 'e'], edges);
+        let expected = HashMap::from([('a', 0), ('b', 7), ('c', 9), ('d', 6), ('e', 0), ('f', 9)]);
+        assert_eq!(dist, expected);
+    }
+
+    #[test]
+    fn search_tree_rebuilds_paths() {
+        let (tree, goal) = astar_tree(['a'], edges, |_| 0, |_| false);
+        assert_eq!(goal, None);
+        assert_eq!(tree.path_to(&'d'), Some(Path { cost: 20, nodes: vec!['a', 'c', 'd'] }));
+        assert_eq!(tree.path_to(&'b'), Some(Path { cost: 7, nodes: vec!['a', 'b'] }));
+        assert_eq!(tree.path_to(&'a'), Some(Path { cost: 0, nodes: vec!['a'] }));
+        assert_eq!(tree.path_to(&'x'), None);
+    }
+
+    #[test]
+    fn astar_on_grid_matches_dijkstra() {
+        let lines: Vec<String> = [
+            "1163751742", "1381373672", "2136511328", "3694931569", "7463417111",
+            "1319128137", "1359912421", "3125421639", "1293138521", "2311944581",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+        let grid = Grid::from_lines(&lines, |c| c.to_digit(10).unwrap());
+        let goal = Point2::xy(9, 9);
+        let neighbours = |pos: &Point2| grid.weighted_steps(pos, |_, to| Some(*to)).collect::<Vec<_>>();
+
+        let path = astar([Point2::xy(0, 0)], neighbours, |pos| pos.manhattan(&goal) as u32, |pos| *pos == goal).unwrap();
+        assert_eq!(path.cost, 40);
+        assert_eq!((path.start(), path.end()), (Some(&Point2::xy(0, 0)), Some(&goal)));
+        assert!(path.nodes.windows(2).all(|step| step[0].manhattan(&step[1]) == 1));
+        assert_eq!(path.nodes.iter().skip(1).map(|pos| grid[*pos]).sum::<u32>(), 40);
+        assert_eq!(dijkstra([Point2::xy(0, 0)], neighbours, |pos| *pos == goal).unwrap().cost, 40);
+    }
+}